@@ -0,0 +1,100 @@
+// run with `cargo bench`. each case prints the best of several runs, so noise from other processes
+// mostly drops out, and builds its input with the encoder so no sample files are needed
+use std::hint::black_box;
+use std::time::{ Duration, Instant };
+use image::{ Rgba, RgbaImage };
+use rayon::prelude::*;
+use paradoodle::{ CompressionType, DecodeOptions, ImageDef, PixelDataType, DEFAULT_UNKNOWN, IMAGE_DEF_SIZE, decode };
+use paradoodle::encode::{ EncodeOptions, ImageMetadata, encode, to_rgb565 };
+
+const RUNS: usize = 5;
+
+fn best_of(mut f: impl FnMut()) -> Duration {
+	(0..RUNS).map(|_| {
+		let start = Instant::now();
+		f();
+		start.elapsed()
+	}).min().unwrap_or_default()
+}
+
+fn report(name: &str, time: Duration) {
+	println!("{:<48} {:>10.2} ms", name, time.as_secs_f64() * 1000.0);
+}
+
+// 4 bpp, bytewise compressed images of 64x64 sprites, with num_palettes palettes each. pixels come in
+// short runs of one color, like drawn art, so compression has something to work with
+fn make_container(num_images: usize, num_sprites: usize, num_palettes: usize) -> Vec<u8> {
+	let color = |p: usize, c: usize| Rgba([(c * 16) as u8, (p * 32) as u8, 255 - (c * 16) as u8, 255]);
+	let palettes: Vec<Vec<u16>> = (0..num_palettes).map(|p| (0..16).map(|c| to_rgb565(color(p, c))).collect()).collect();
+	let mut state = 1u32;
+	let images: Vec<(ImageMetadata, RgbaImage)> = (0..num_images).map(|_| {
+		let def = ImageDef {
+			data_length: 0,
+			has_transparency: false,
+			is_encrypted: false,
+			compression: CompressionType::Bytewise,
+			pixel_data_type: PixelDataType::Bpp(4),
+			num_sprites,
+			sprite_width_px: 64,
+			sprite_height_px: 64,
+			offset_x: 0,
+			offset_y: 0,
+			image_width: 1,
+			image_height: 1,
+			unknown: DEFAULT_UNKNOWN,
+			num_palettes,
+			transparent_color_index: 0,
+			palette_data_offset: IMAGE_DEF_SIZE,
+			pixel_data_offset: IMAGE_DEF_SIZE,
+			padding: 0,
+			num_subimages: num_sprites
+		};
+		let mut spritesheet = RgbaImage::new(64 * num_sprites as u32, 64);
+		let mut c = 0;
+		for (i, pixel) in spritesheet.pixels_mut().enumerate() {
+			if i % 5 == 0 {
+				state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+				c = (state >> 28) as usize;
+			}
+			*pixel = color(0, c);
+		}
+		(ImageMetadata { offset: 0, def, color_format: Default::default(), palettes: palettes.clone(), row_aligned: false, tile_order: Default::default(), endian: Default::default() }, spritesheet)
+	}).collect();
+	encode(&images, &EncodeOptions::default()).expect("benchmark images should encode")
+}
+
+fn main() {
+	let options = DecodeOptions::default();
+	let data = make_container(200, 8, 8);
+	println!("container: {} images, {} bytes, {} threads", 200, data.len(), rayon::current_num_threads());
+
+	// the whole container parsed, then every sprite decompressed and drawn with its first palette
+	report("decode container", best_of(|| {
+		for image in decode(&data).expect("benchmark container should decode") {
+			black_box(paradoodle::make_sprites(&image.def, &image.pixel_data_per_sprite, &image.palettes[0], &options).unwrap());
+		}
+	}));
+
+	let images = decode(&data).expect("benchmark container should decode");
+
+	// sprite indices decompressed once per image, as make_spritesheet does, against once per palette
+	// row, as it did before the indices were shared between rows
+	let indices = |image: &paradoodle::DecodedImage| {
+		black_box(paradoodle::decode_sprite_indices(&image.def, &image.pixel_data_per_sprite, 4, &options).unwrap());
+	};
+	report("sprite indices, once per image", best_of(|| images.iter().for_each(indices)));
+	report("sprite indices, once per palette", best_of(|| images.iter().for_each(|image| image.palettes.iter().for_each(|_| indices(image)))));
+	report("sprites, once per palette", best_of(|| images.iter().for_each(|image| {
+		for palette in &image.palettes {
+			black_box(paradoodle::make_sprites(&image.def, &image.pixel_data_per_sprite, palette, &options).unwrap());
+		}
+	})));
+
+	// every image decoded and drawn into a spritesheet, one image at a time and then in parallel
+	let spritesheets = |image: &paradoodle::DecodedImage| {
+		black_box(paradoodle::make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &options).unwrap());
+	};
+	let single_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+	report("spritesheets, 1 thread", best_of(|| single_thread.install(|| images.iter().for_each(spritesheets))));
+	report("spritesheets, every thread", best_of(|| images.par_iter().for_each(spritesheets)));
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use paradoodle::{ Endian, read_image_def };
+
+// any bytes at all should parse into a header or an error, never a panic
+fuzz_target!(|data: &[u8]| {
+	let _ = read_image_def(data, 0, Endian::Little);
+	let _ = read_image_def(data, 0, Endian::Big);
+});
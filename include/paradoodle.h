@@ -0,0 +1,65 @@
+#ifndef PARADOODLE_H
+#define PARADOODLE_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define PDL_OK 0
+
+#define PDL_ERR_NULL_POINTER -1
+
+#define PDL_ERR_INDEX_OUT_OF_RANGE -2
+
+#define PDL_ERR_DECODE -3
+
+typedef struct PdlHandle PdlHandle;
+
+/**
+ * Copies `len` bytes of file data and reads its offset table.
+ * Returns null if `data` is null or the offset table can't be read.
+ *
+ * # Safety
+ *
+ * `data` must point to at least `len` readable bytes.
+ */
+struct PdlHandle *pdl_open(const uint8_t *data, uintptr_t len);
+
+/**
+ * Number of images in the file, or 0 if `handle` is null.
+ *
+ * # Safety
+ *
+ * `handle` must be null or a pointer returned by `pdl_open` that hasn't been freed.
+ */
+uintptr_t pdl_image_count(const struct PdlHandle *handle);
+
+/**
+ * Decodes the image at `index` into a spritesheet with one row per palette, as RGBA8 pixels row by row.
+ * On success, `out_rgba` points to `out_w * out_h * 4` bytes owned by the handle, which stay valid until
+ * the next call to `pdl_decode_image` or `pdl_free`. Returns `PDL_OK` or one of the negative `PDL_ERR_*` codes.
+ *
+ * # Safety
+ *
+ * `handle` must be a pointer returned by `pdl_open` that hasn't been freed, and the out pointers must be
+ * valid for writes.
+ */
+int32_t pdl_decode_image(struct PdlHandle *handle,
+                         uintptr_t index,
+                         const uint8_t **out_rgba,
+                         uint32_t *out_w,
+                         uint32_t *out_h);
+
+/**
+ * Frees a handle returned by `pdl_open`, along with any pixels it holds. Does nothing if `handle` is null.
+ *
+ * # Safety
+ *
+ * `handle` must be null or a pointer returned by `pdl_open` that hasn't already been freed.
+ */
+void pdl_free(struct PdlHandle *handle);
+
+#endif  /* PARADOODLE_H */
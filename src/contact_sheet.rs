@@ -0,0 +1,80 @@
+use image::{ Rgba, RgbaImage, imageops };
+use image::imageops::FilterType;
+
+const THUMBNAIL_SIZE: u32 = 64;
+const LABEL_HEIGHT: u32 = 12;
+const PADDING: u32 = 4;
+
+// 3x5 pixel digits, one row per byte with the leftmost pixel in the highest of the 3 bits
+const DIGITS: [[u8; 5]; 10] = [
+	[0b111, 0b101, 0b101, 0b101, 0b111],
+	[0b010, 0b110, 0b010, 0b010, 0b111],
+	[0b111, 0b001, 0b111, 0b100, 0b111],
+	[0b111, 0b001, 0b111, 0b001, 0b111],
+	[0b101, 0b101, 0b111, 0b001, 0b001],
+	[0b111, 0b100, 0b111, 0b001, 0b111],
+	[0b111, 0b100, 0b111, 0b101, 0b111],
+	[0b111, 0b001, 0b010, 0b010, 0b010],
+	[0b111, 0b101, 0b111, 0b101, 0b111],
+	[0b111, 0b101, 0b111, 0b001, 0b111]
+];
+
+// lays out a thumbnail of every spritesheet in a grid, each labeled with its image index. without a
+// number of columns, the grid is as close to square as it can be
+pub fn make_contact_sheet(spritesheets: &[(usize, RgbaImage)], columns: Option<usize>) -> RgbaImage {
+	let columns = match columns {
+		Some(columns) => columns.max(1) as u32,
+		None => (spritesheets.len() as f64).sqrt().ceil().max(1.0) as u32
+	};
+	let rows = (spritesheets.len() as u32).div_ceil(columns);
+	let cell_width = THUMBNAIL_SIZE + PADDING;
+	let cell_height = THUMBNAIL_SIZE + LABEL_HEIGHT + PADDING;
+	let mut img = RgbaImage::from_pixel(columns * cell_width + PADDING, rows * cell_height + PADDING, Rgba([48, 48, 48, 255]));
+
+	for (n, (index, spritesheet)) in spritesheets.iter().enumerate() {
+		let x = (n as u32 % columns) * cell_width + PADDING;
+		let y = (n as u32 / columns) * cell_height + PADDING;
+		let thumbnail = make_thumbnail(spritesheet);
+		let offset_x = (THUMBNAIL_SIZE - thumbnail.width()) / 2;
+		let offset_y = (THUMBNAIL_SIZE - thumbnail.height()) / 2;
+		imageops::overlay(&mut img, &thumbnail, (x + offset_x) as i64, (y + offset_y) as i64);
+		draw_number(&mut img, *index, x, y + THUMBNAIL_SIZE + 2);
+	}
+
+	img
+}
+
+// nearest-neighbor scale to fit inside the thumbnail, keeping the aspect ratio
+fn make_thumbnail(spritesheet: &RgbaImage) -> RgbaImage {
+	let (width, height) = spritesheet.dimensions();
+	if width == 0 || height == 0 {
+		return RgbaImage::new(0, 0);
+	}
+	let scale = (THUMBNAIL_SIZE as f64 / width as f64).min(THUMBNAIL_SIZE as f64 / height as f64);
+	let thumbnail_width = ((width as f64 * scale) as u32).clamp(1, THUMBNAIL_SIZE);
+	let thumbnail_height = ((height as f64 * scale) as u32).clamp(1, THUMBNAIL_SIZE);
+	imageops::resize(spritesheet, thumbnail_width, thumbnail_height, FilterType::Nearest)
+}
+
+// draws digits at 2x scale, so each one is 6x10 pixels
+fn draw_number(img: &mut RgbaImage, number: usize, x: u32, y: u32) {
+	for (d, digit) in number.to_string().bytes().enumerate() {
+		let glyph = DIGITS[(digit - b'0') as usize];
+		for (row, bits) in glyph.iter().enumerate() {
+			for col in 0..3 {
+				if bits & (0b100 >> col) == 0 {
+					continue;
+				}
+				for dy in 0..2 {
+					for dx in 0..2 {
+						let px = x + d as u32 * 8 + col * 2 + dx;
+						let py = y + row as u32 * 2 + dy;
+						if px < img.width() && py < img.height() {
+							img.put_pixel(px, py, Rgba([255, 255, 255, 255]));
+						}
+					}
+				}
+			}
+		}
+	}
+}
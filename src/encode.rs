@@ -0,0 +1,577 @@
+use bytes::BufMut;
+use image::{ Rgba, RgbaImage, imageops };
+use serde::{ Serialize, Deserialize };
+use thiserror::Error;
+use crate::{ ColorExpansion, ColorFormat, CompressionType, Endian, PixelDataType, ImageDef, TileOrder, IMAGE_DEF_SIZE, DEFAULT_DECRYPTION_KEY, FLAG_TRANSPARENCY, FLAG_COMPRESS_BYTEWISE, FLAG_COMPRESS_WORDWISE, FLAG_ENCRYPTED, decrypt_pixel_data, compress_bytewise, compress_wordwise };
+use crate::quantize::quantize;
+
+// header values plus raw RGB565 palettes, enough to rebuild an image from its spritesheet
+#[derive(Serialize, Deserialize)]
+pub struct ImageMetadata {
+	pub offset: u32,
+	#[serde(flatten)]
+	pub def: ImageDef,
+	// raw palette values, in color_format
+	#[serde(default)]
+	pub color_format: ColorFormat,
+	pub palettes: Vec<Vec<u16>>,
+	// indexed sprite rows were padded to whole bytes, as with --row-aligned
+	#[serde(default)]
+	pub row_aligned: bool,
+	// the order pixels are stored in within each indexed sprite, as with --tile-order
+	#[serde(default)]
+	pub tile_order: TileOrder,
+	// byte order of the header, colors and compressed sprite offsets, as with --endian
+	#[serde(default)]
+	pub endian: Endian
+}
+
+#[derive(Debug, Error)]
+pub enum EncodeError {
+	#[error("spritesheet is {width}x{height}, expected at least {expected_width}x{expected_height}")]
+	SheetTooSmall { width: u32, height: u32, expected_width: u32, expected_height: u32 },
+	#[error("color {color:?} in sprite {sprite} is not in the palette")]
+	ColorNotInPalette { sprite: usize, color: Rgba<u8> },
+	#[error("{0} bits per pixel can't be encoded")]
+	UnsupportedBpp(usize),
+	#[error("expected {expected} sprites, found {found}")]
+	WrongSpriteCount { expected: usize, found: usize },
+	#[error("sprite {sprite} is {width}x{height}, expected {expected_width}x{expected_height}")]
+	WrongSpriteSize { sprite: usize, width: u32, height: u32, expected_width: u32, expected_height: u32 },
+	#[error("sprites use {0} colors, but a palette holds at most 256")]
+	TooManyColors(usize),
+	#[error("wordwise compression needs a multiple of 4 bytes, got {0}")]
+	UnalignedWords(usize),
+	#[error("image {index} is {endian:?} endian, but the offset table is {expected:?} endian like the first image")]
+	MixedEndian { index: usize, endian: Endian, expected: Endian }
+}
+
+pub fn to_rgb565(color: Rgba<u8>) -> u16 {
+	let r = (color[0] as u16 * 31 + 127) / 255;
+	let g = (color[1] as u16 * 63 + 127) / 255;
+	let b = (color[2] as u16 * 31 + 127) / 255;
+	(r << 11) | (g << 5) | b
+}
+
+pub fn to_rgb555(color: Rgba<u8>) -> u16 {
+	let r = (color[0] as u16 * 31 + 127) / 255;
+	let g = (color[1] as u16 * 31 + 127) / 255;
+	let b = (color[2] as u16 * 31 + 127) / 255;
+	(r << 10) | (g << 5) | b
+}
+
+impl ColorFormat {
+	pub fn to_raw(self, color: Rgba<u8>) -> u16 {
+		match self {
+			ColorFormat::Rgb565 => to_rgb565(color),
+			ColorFormat::Rgb555 => to_rgb555(color),
+			ColorFormat::Argb1555 => to_rgb555(color) | if color[3] >= 128 { 0x8000 } else { 0 }
+		}
+	}
+}
+
+pub struct EncodeOptions {
+	// XOR key for images whose metadata says they're encrypted
+	pub encryption_key: u8,
+	// spread the rounding error of direct color pixels to their neighbors, to avoid banding on gradients
+	pub dither: bool,
+	// replace the palettes of indexed images with one built from the spritesheet's colors, for
+	// spritesheets that were edited with colors outside the original palette
+	pub quantize: bool
+}
+
+impl Default for EncodeOptions {
+	fn default() -> Self {
+		EncodeOptions {
+			encryption_key: DEFAULT_DECRYPTION_KEY,
+			dither: false,
+			quantize: false
+		}
+	}
+}
+
+pub fn encode(images: &[(ImageMetadata, RgbaImage)], options: &EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+	let encoded_images: Vec<Vec<u8>> = images.iter()
+		.map(|(metadata, spritesheet)| encode_image(metadata, spritesheet, options))
+		.collect::<Result<_, _>>()?;
+
+	// a file has a single byte order, since the offset table is shared by every image
+	let endian = images.first().map_or(Endian::default(), |(metadata, _)| metadata.endian);
+	if let Some(index) = images.iter().position(|(metadata, _)| metadata.endian != endian) {
+		return Err(EncodeError::MixedEndian { index, endian: images[index].0.endian, expected: endian });
+	}
+
+	// write offset table, then each image in order
+	let mut data = Vec::new();
+	let mut image_offset = encoded_images.len() * 4;
+	for encoded_image in &encoded_images {
+		endian.put_u32(&mut data, image_offset as u32);
+		image_offset += encoded_image.len();
+	}
+	for encoded_image in &encoded_images {
+		data.extend(encoded_image);
+	}
+
+	Ok(data)
+}
+
+// lays out individual sprites as an 8 bpp, uncompressed, unencrypted image with a single palette
+// made from the colors they use, ready for encode
+pub fn sprites_to_image(def: &ImageDef, sprites: &[RgbaImage]) -> Result<(ImageMetadata, RgbaImage), EncodeError> {
+	if sprites.len() != def.num_sprites {
+		return Err(EncodeError::WrongSpriteCount { expected: def.num_sprites, found: sprites.len() });
+	}
+	let (expected_width, expected_height) = (def.sprite_width_px as u32, def.sprite_height_px as u32);
+	for (sprite, img) in sprites.iter().enumerate() {
+		if img.dimensions() != (expected_width, expected_height) {
+			return Err(EncodeError::WrongSpriteSize { sprite, width: img.width(), height: img.height(), expected_width, expected_height });
+		}
+	}
+
+	// collect colors in order of first use, keeping index 0 for transparency
+	let has_transparency = sprites.iter().any(|img| img.pixels().any(|color| color[3] == 0));
+	let mut palette: Vec<u16> = if has_transparency { vec![0] } else { Vec::new() };
+	for color in sprites.iter().flat_map(|img| img.pixels()).filter(|color| color[3] != 0) {
+		let value = to_rgb565(*color);
+		if !palette[(has_transparency as usize)..].contains(&value) {
+			palette.push(value);
+		}
+	}
+	if palette.len() > 256 {
+		return Err(EncodeError::TooManyColors(palette.len()));
+	}
+
+	// place sprites in a single row of subimages, like make_spritesheet
+	let subimage_width = def.image_width * def.sprite_width_px;
+	let sprites_per_subimage = def.image_width * def.image_height;
+	let mut spritesheet = RgbaImage::new(
+		(def.num_subimages * subimage_width) as u32,
+		(def.image_height * def.sprite_height_px) as u32
+	);
+	for (k, sprite) in sprites.iter().enumerate() {
+		let j = k / sprites_per_subimage;
+		let cell = k % sprites_per_subimage;
+		let x = j * subimage_width + (cell % def.image_width) * def.sprite_width_px;
+		let y = (cell / def.image_width) * def.sprite_height_px;
+		for (px, py, color) in sprite.enumerate_pixels() {
+			spritesheet.put_pixel(x as u32 + px, y as u32 + py, *color);
+		}
+	}
+
+	let mut def = def.clone();
+	def.has_transparency = has_transparency;
+	def.transparent_color_index = 0;
+	def.is_encrypted = false;
+	def.compression = CompressionType::None;
+	def.pixel_data_type = PixelDataType::Bpp(8);
+	def.num_palettes = 1;
+	let metadata = ImageMetadata { offset: 0, def, color_format: ColorFormat::Rgb565, palettes: vec![palette], row_aligned: false, tile_order: TileOrder::Linear, endian: Endian::Little };
+	Ok((metadata, spritesheet))
+}
+
+pub fn encode_image(metadata: &ImageMetadata, spritesheet: &RgbaImage, options: &EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+	let def = &metadata.def;
+
+	// make sure the spritesheet can hold every sprite
+	let subimage_width = def.image_width * def.sprite_width_px;
+	let subimage_height = def.image_height * def.sprite_height_px;
+	let expected_width = (def.num_subimages * subimage_width) as u32;
+	let expected_height = subimage_height as u32;
+	if spritesheet.width() < expected_width || spritesheet.height() < expected_height {
+		return Err(EncodeError::SheetTooSmall {
+			width: spritesheet.width(),
+			height: spritesheet.height(),
+			expected_width,
+			expected_height
+		});
+	}
+
+	let dithered;
+	let spritesheet = if options.dither && let PixelDataType::Direct = def.pixel_data_type {
+		dithered = dither(spritesheet, metadata.color_format);
+		&dithered
+	} else {
+		spritesheet
+	};
+
+	let quantized;
+	let (metadata, spritesheet) = if options.quantize && let PixelDataType::Bpp(bpp) = def.pixel_data_type {
+		quantized = quantize_spritesheet(metadata, spritesheet, bpp, expected_width, expected_height);
+		(&quantized.0, &quantized.1)
+	} else {
+		(metadata, spritesheet)
+	};
+	let def = &metadata.def;
+
+	// pack each sprite from the first palette row, matching colors by their raw values so it doesn't
+	// matter how the spritesheet's colors were expanded
+	let palette = metadata.palettes.first().map_or(&[][..], |values| &values[..]);
+	let mut pixel_data_per_sprite = Vec::new();
+	for k in 0..def.num_sprites {
+		let sprites_per_subimage = def.image_width * def.image_height;
+		let j = k / sprites_per_subimage;
+		let cell = k % sprites_per_subimage;
+		let x = j * subimage_width + (cell % def.image_width) * def.sprite_width_px;
+		let y = (cell / def.image_width) * def.sprite_height_px;
+		let pixel_data = match def.pixel_data_type {
+			PixelDataType::Bpp(bpp) => pack_indexed_sprite(spritesheet, metadata, palette, x, y, bpp, k)?,
+			PixelDataType::Direct => pack_direct_sprite(spritesheet, def, x, y, metadata.color_format, metadata.endian)
+		};
+		pixel_data_per_sprite.push(pixel_data);
+	}
+
+	// compress and encrypt sprites
+	let pixel_data = if let CompressionType::None = def.compression {
+		let pixel_data: Vec<u8> = pixel_data_per_sprite.concat();
+		if def.is_encrypted { decrypt_pixel_data(&pixel_data, options.encryption_key) } else { pixel_data }
+	} else {
+		let sprite_data = pixel_data_per_sprite.iter().map(|pixel_data| {
+			let compressed = match def.compression {
+				CompressionType::Bytewise => compress_bytewise(pixel_data),
+				_ => {
+					// pad to a whole number of words
+					let mut pixel_data = pixel_data.clone();
+					pixel_data.resize(pixel_data.len().next_multiple_of(4), 0);
+					compress_wordwise(&pixel_data)?
+				}
+			};
+			Ok(if def.is_encrypted { decrypt_pixel_data(&compressed, options.encryption_key) } else { compressed })
+		}).collect::<Result<Vec<Vec<u8>>, EncodeError>>()?;
+
+		// offsets + lengths, followed by the sprite data
+		let mut pixel_data = Vec::new();
+		let mut sprite_offset = sprite_data.len() * 8;
+		for data in &sprite_data {
+			metadata.endian.put_u32(&mut pixel_data, sprite_offset as u32);
+			metadata.endian.put_u32(&mut pixel_data, data.len() as u32);
+			sprite_offset += data.len();
+		}
+		for data in &sprite_data {
+			pixel_data.extend(data);
+		}
+		pixel_data
+	};
+
+	// palette data
+	let mut palette_data = Vec::new();
+	if let PixelDataType::Bpp(bpp) = def.pixel_data_type {
+		let colors_per_palette = 2usize.pow(bpp as u32);
+		for p in 0..def.num_palettes {
+			for c in 0..colors_per_palette {
+				let value = metadata.palettes.get(p).and_then(|values| values.get(c)).copied().unwrap_or(0);
+				metadata.endian.put_u16(&mut palette_data, value);
+			}
+		}
+	}
+
+	// header
+	let palette_data_offset = IMAGE_DEF_SIZE;
+	let pixel_data_offset = palette_data_offset + palette_data.len();
+	let data_length = pixel_data_offset + pixel_data.len();
+	let mut data = Vec::new();
+	write_image_def(&mut data, def, data_length, palette_data_offset, pixel_data_offset, metadata.endian)?;
+	data.extend(palette_data);
+	data.extend(pixel_data);
+
+	Ok(data)
+}
+
+// swaps every palette for a single one made from the colors in the first palette row, and redraws
+// that row in the new palette's colors so every pixel matches an entry exactly
+fn quantize_spritesheet(metadata: &ImageMetadata, spritesheet: &RgbaImage, bpp: usize, width: u32, height: u32) -> (ImageMetadata, RgbaImage) {
+	let row = imageops::crop_imm(spritesheet, 0, 0, width, height).to_image();
+	let (palette, indices) = quantize(&row, bpp);
+	let has_transparency = palette.first().is_some_and(|color| color[3] == 0);
+
+	let mut quantized = RgbaImage::new(width, height);
+	for (pixel, index) in quantized.pixels_mut().zip(indices) {
+		*pixel = palette[index as usize];
+	}
+
+	let mut def = metadata.def.clone();
+	def.has_transparency = has_transparency;
+	def.transparent_color_index = 0;
+	def.num_palettes = 1;
+	let palettes = vec![palette.iter().map(|color| metadata.color_format.to_raw(*color)).collect()];
+	(ImageMetadata { offset: metadata.offset, def, color_format: metadata.color_format, palettes, row_aligned: metadata.row_aligned, tile_order: metadata.tile_order, endian: metadata.endian }, quantized)
+}
+
+fn write_image_def(data: &mut Vec<u8>, def: &ImageDef, data_length: usize, palette_data_offset: usize, pixel_data_offset: usize, endian: Endian) -> Result<(), EncodeError> {
+	endian.put_u32(data, data_length as u32);
+
+	// write flags
+	let mut flags = 0;
+	if def.has_transparency {
+		flags |= FLAG_TRANSPARENCY;
+	}
+	match def.compression {
+		CompressionType::None => {},
+		CompressionType::Bytewise => flags |= FLAG_COMPRESS_BYTEWISE,
+		CompressionType::Wordwise => flags |= FLAG_COMPRESS_WORDWISE
+	}
+	if def.is_encrypted {
+		flags |= FLAG_ENCRYPTED;
+	}
+	data.put_u8(flags);
+
+	// write bpp
+	data.put_u8(match def.pixel_data_type {
+		PixelDataType::Bpp(1) => 0,
+		PixelDataType::Bpp(2) => 1,
+		PixelDataType::Bpp(4) => 2,
+		PixelDataType::Bpp(8) => 3,
+		PixelDataType::Bpp(bpp) => return Err(EncodeError::UnsupportedBpp(bpp)),
+		PixelDataType::Direct => 4
+	});
+
+	// write other properties
+	endian.put_u16(data, def.num_sprites as u16);
+	data.put_u8(def.sprite_width_px as u8);
+	data.put_u8(def.sprite_height_px as u8);
+	data.put_i8(def.offset_x);
+	data.put_i8(def.offset_y);
+	data.put_u8(def.image_width as u8);
+	data.put_u8(def.image_height as u8);
+	data.put_u8(def.unknown);
+	data.put_u8(def.num_palettes as u8);
+	endian.put_u16(data, def.transparent_color_index);
+	endian.put_u16(data, palette_data_offset as u16);
+	endian.put_u16(data, pixel_data_offset as u16);
+	endian.put_u16(data, def.padding);
+
+	Ok(())
+}
+
+fn pack_indexed_sprite(spritesheet: &RgbaImage, metadata: &ImageMetadata, palette: &[u16], x: usize, y: usize, bpp: usize, sprite: usize) -> Result<Vec<u8>, EncodeError> {
+	let def = &metadata.def;
+	let num_pixels = def.sprite_width_px * def.sprite_height_px;
+	let mut bytes = vec![0; def.bytes_per_sprite(metadata.row_aligned)];
+	// rows padded to whole bytes start on a byte boundary, otherwise the sprite is one bit stream
+	let bits_per_row = if metadata.row_aligned { (def.sprite_width_px * bpp).div_ceil(8) * 8 } else { def.sprite_width_px * bpp };
+
+	// add each palette index to the bit stream in least-significant order, taking pixels in the order
+	// they're stored. tiles that hang off the edge of the sprite are padded with index 0
+	for i in 0..num_pixels {
+		let (px, py) = metadata.tile_order.position(i, def.sprite_width_px);
+		if px >= def.sprite_width_px || py >= def.sprite_height_px {
+			continue;
+		}
+		let color = *spritesheet.get_pixel((x + px) as u32, (y + py) as u32);
+		let index = match def.transparent_index() {
+			Some(index) if color[3] == 0 => index,
+			_ => {
+				// the transparent entry always draws transparent, whatever its color
+				let value = metadata.color_format.to_raw(color);
+				palette.iter().enumerate()
+					.position(|(i, c)| *c == value && def.transparent_index() != Some(i))
+					.ok_or(EncodeError::ColorNotInPalette { sprite, color })?
+			}
+		};
+		for b in 0..bpp {
+			let bit = (i / def.sprite_width_px) * bits_per_row + (i % def.sprite_width_px) * bpp + b;
+			bytes[bit / 8] |= (((index >> b) & 1) as u8) << (bit % 8);
+		}
+	}
+
+	Ok(bytes)
+}
+
+// Floyd-Steinberg dithering: snaps every color to one the format can hold, passing the difference on
+// to the pixels right and below. fully transparent pixels are left alone and don't take part
+pub fn dither(spritesheet: &RgbaImage, color_format: ColorFormat) -> RgbaImage {
+	let (width, height) = (spritesheet.width() as usize, spritesheet.height() as usize);
+	let mut errors = vec![[0.0f32; 3]; width * height];
+	let mut dithered = spritesheet.clone();
+	for y in 0..height {
+		for x in 0..width {
+			let color = *spritesheet.get_pixel(x as u32, y as u32);
+			if color[3] == 0 {
+				continue;
+			}
+			let mut wanted = color;
+			for channel in 0..3 {
+				wanted[channel] = (color[channel] as f32 + errors[y * width + x][channel]).round().clamp(0.0, 255.0) as u8;
+			}
+			let mut snapped = color_format.parse(color_format.to_raw(wanted), ColorExpansion::default());
+			snapped[3] = color[3];
+			dithered.put_pixel(x as u32, y as u32, snapped);
+
+			for channel in 0..3 {
+				let error = color[channel] as f32 + errors[y * width + x][channel] - snapped[channel] as f32;
+				let mut spread = |dx: isize, dy: usize, weight: f32| {
+					let nx = x as isize + dx;
+					if nx >= 0 && (nx as usize) < width && y + dy < height {
+						errors[(y + dy) * width + nx as usize][channel] += error * weight;
+					}
+				};
+				spread(1, 0, 7.0 / 16.0);
+				spread(-1, 1, 3.0 / 16.0);
+				spread(0, 1, 5.0 / 16.0);
+				spread(1, 1, 1.0 / 16.0);
+			}
+		}
+	}
+	dithered
+}
+
+fn pack_direct_sprite(spritesheet: &RgbaImage, def: &ImageDef, x: usize, y: usize, color_format: ColorFormat, endian: Endian) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	for py in y..(y + def.sprite_height_px) {
+		for px in x..(x + def.sprite_width_px) {
+			let color = *spritesheet.get_pixel(px as u32, py as u32);
+			let value = match def.transparent_color() {
+				Some(value) if color[3] == 0 => value,
+				_ => color_format.to_raw(color)
+			};
+			endian.put_u16(&mut bytes, value);
+		}
+	}
+	bytes
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ DecodeOptions, DEFAULT_UNKNOWN, decode, make_sprites, make_spritesheet, parse_rgb565 };
+
+	// an uncompressed image with one palette and a subimage per sprite
+	fn test_def(pixel_data_type: PixelDataType, num_sprites: usize, sprite_width_px: usize, sprite_height_px: usize) -> ImageDef {
+		ImageDef {
+			data_length: 0,
+			has_transparency: true,
+			is_encrypted: false,
+			compression: CompressionType::None,
+			pixel_data_type,
+			num_sprites,
+			sprite_width_px,
+			sprite_height_px,
+			offset_x: 0,
+			offset_y: 0,
+			image_width: 1,
+			image_height: 1,
+			unknown: DEFAULT_UNKNOWN,
+			num_palettes: 1,
+			transparent_color_index: 0,
+			palette_data_offset: IMAGE_DEF_SIZE,
+			pixel_data_offset: IMAGE_DEF_SIZE,
+			padding: 0,
+			num_subimages: num_sprites
+		}
+	}
+
+	fn decode_spritesheet(data: &[u8]) -> RgbaImage {
+		let image = decode(data).unwrap().remove(0);
+		make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &DecodeOptions::default()).unwrap()
+	}
+
+	#[test]
+	fn quantize_rebuilds_the_palette_of_edited_spritesheets() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255]), Rgba([40, 80, 200, 255])];
+		let spritesheet = RgbaImage::from_fn(8, 4, |x, y| colors[((x + y) % 4) as usize]);
+		let palette = vec![0, to_rgb565(colors[1]), to_rgb565(colors[2]), 0x1234];
+		let metadata = ImageMetadata { offset: 0, def: test_def(PixelDataType::Bpp(2), 2, 4, 4), color_format: ColorFormat::Rgb565, palettes: vec![palette], row_aligned: false, tile_order: TileOrder::Linear, endian: Endian::Little };
+
+		let images = [(metadata, spritesheet.clone())];
+		assert!(matches!(encode(&images, &EncodeOptions::default()), Err(EncodeError::ColorNotInPalette { .. })));
+
+		let data = encode(&images, &EncodeOptions { quantize: true, ..EncodeOptions::default() }).unwrap();
+		let decoded = decode_spritesheet(&data);
+		assert_eq!(decoded.dimensions(), spritesheet.dimensions());
+		for (expected, got) in spritesheet.pixels().zip(decoded.pixels()) {
+			assert_eq!(expected[3], got[3]);
+			if expected[3] != 0 {
+				assert_eq!(to_rgb565(*expected), to_rgb565(*got));
+			}
+		}
+	}
+
+	#[test]
+	fn re_encoded_sprites_decode_identically() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 130, 8, 255]), Rgba([255, 255, 255, 255])];
+		let sprites: Vec<RgbaImage> = (0..3)
+			.map(|k| RgbaImage::from_fn(4, 3, |x, y| colors[((x * 3 + y + k) % 4) as usize]))
+			.collect();
+		let (metadata, spritesheet) = sprites_to_image(&test_def(PixelDataType::Bpp(8), 3, 4, 3), &sprites).unwrap();
+		let data = encode(&[(metadata, spritesheet)], &EncodeOptions::default()).unwrap();
+
+		let image = decode(&data).unwrap().remove(0);
+		let decoded = make_sprites(&image.def, &image.pixel_data_per_sprite, &image.palettes[0], &DecodeOptions::default()).unwrap();
+		assert_eq!(decoded, sprites);
+	}
+
+	#[test]
+	fn rgb565_round_trips_within_rounding() {
+		for r in (0..=255).step_by(3) {
+			for g in (0..=255).step_by(5) {
+				for b in (0..=255).step_by(7) {
+					let color = Rgba([r as u8, g as u8, b as u8, 255]);
+					let back = parse_rgb565(to_rgb565(color), ColorExpansion::default());
+					for (channel, tolerance) in [(0, 4), (1, 2), (2, 4)] {
+						assert!((color[channel] as i32 - back[channel] as i32).abs() <= tolerance, "{:?} came back as {:?}", color, back);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn compressed_images_round_trip() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255]), Rgba([0, 0, 255, 255])];
+		let spritesheet = RgbaImage::from_fn(9, 3, |x, y| colors[((x * x + y) % 4) as usize]);
+		let palette: Vec<u16> = colors.iter().map(|color| to_rgb565(*color)).collect();
+		for compression in [CompressionType::Bytewise, CompressionType::Wordwise] {
+			// 3x3 sprites at 4 bpp don't fill a whole number of words, so wordwise has to pad them
+			let mut def = test_def(PixelDataType::Bpp(4), 3, 3, 3);
+			def.compression = compression;
+			let metadata = ImageMetadata { offset: 0, def, color_format: ColorFormat::Rgb565, palettes: vec![palette.clone()], row_aligned: false, tile_order: TileOrder::Linear, endian: Endian::Little };
+			let data = encode(&[(metadata, spritesheet.clone())], &EncodeOptions::default()).unwrap();
+			assert_eq!(decode_spritesheet(&data), spritesheet);
+		}
+	}
+
+	#[test]
+	fn row_aligned_images_round_trip() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255])];
+		let spritesheet = RgbaImage::from_fn(10, 3, |x, y| colors[((x + y * y) % 3 / 2) as usize]);
+		let palette: Vec<u16> = colors.iter().map(|color| to_rgb565(*color)).collect();
+		let metadata = ImageMetadata { offset: 0, def: test_def(PixelDataType::Bpp(1), 2, 5, 3), color_format: ColorFormat::Rgb565, palettes: vec![palette], row_aligned: true, tile_order: TileOrder::Linear, endian: Endian::Little };
+		let data = encode(&[(metadata, spritesheet.clone())], &EncodeOptions::default()).unwrap();
+
+		// offset table, header, a 2 color palette and a byte per 5 pixel row, rather than 15 bits per sprite
+		assert_eq!(data.len(), 4 + IMAGE_DEF_SIZE + 4 + 2 * 3);
+		let options = DecodeOptions { row_aligned: true, strict: true, ..DecodeOptions::default() };
+		let image = crate::decode_image(&data, 4, &options).unwrap();
+		assert_eq!(make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &options).unwrap(), spritesheet);
+	}
+
+	#[test]
+	fn tiled_images_round_trip() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255]), Rgba([0, 0, 255, 255])];
+		let spritesheet = RgbaImage::from_fn(16, 16, |x, y| colors[((x / 3 + y * 5) % 4) as usize]);
+		let palette: Vec<u16> = colors.iter().map(|color| to_rgb565(*color)).collect();
+		let metadata = ImageMetadata { offset: 0, def: test_def(PixelDataType::Bpp(4), 1, 16, 16), color_format: ColorFormat::Rgb565, palettes: vec![palette], row_aligned: false, tile_order: TileOrder::Tiled8x8, endian: Endian::Little };
+		let data = encode(&[(metadata, spritesheet.clone())], &EncodeOptions::default()).unwrap();
+
+		let options = DecodeOptions { tile_order: TileOrder::Tiled8x8, ..DecodeOptions::default() };
+		let image = decode(&data).unwrap().remove(0);
+		assert_eq!(make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &options).unwrap(), spritesheet);
+		assert_ne!(decode_spritesheet(&data), spritesheet);
+	}
+
+	#[test]
+	fn big_endian_images_round_trip() {
+		let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255]), Rgba([0, 0, 255, 255])];
+		let spritesheet = RgbaImage::from_fn(8, 8, |x, y| colors[((x + y * 3) % 4) as usize]);
+		let palette: Vec<u16> = colors.iter().map(|color| to_rgb565(*color)).collect();
+		let metadata = |endian| ImageMetadata { offset: 0, def: test_def(PixelDataType::Bpp(4), 1, 8, 8), color_format: ColorFormat::Rgb565, palettes: vec![palette.clone()], row_aligned: false, tile_order: TileOrder::Linear, endian };
+		let data = encode(&[(metadata(Endian::Big), spritesheet.clone())], &EncodeOptions::default()).unwrap();
+		assert_eq!(&data[..4], &[0, 0, 0, 4]);
+
+		let options = DecodeOptions { endian: Endian::Big, ..DecodeOptions::default() };
+		let image = crate::decode_image(&data, 4, &options).unwrap();
+		assert_eq!(make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &options).unwrap(), spritesheet);
+
+		let mixed = [(metadata(Endian::Big), spritesheet.clone()), (metadata(Endian::Little), spritesheet)];
+		assert!(matches!(encode(&mixed, &EncodeOptions::default()), Err(EncodeError::MixedEndian { index: 1, .. })));
+	}
+}
@@ -0,0 +1,92 @@
+use std::ptr;
+use std::slice;
+use crate::{ build_spritesheet, read_offset_table, DecodeError, Endian };
+
+// return codes for pdl_decode_image; anything negative is an error
+pub const PDL_OK: i32 = 0;
+pub const PDL_ERR_NULL_POINTER: i32 = -1;
+pub const PDL_ERR_INDEX_OUT_OF_RANGE: i32 = -2;
+pub const PDL_ERR_DECODE: i32 = -3;
+
+// an opened file, along with the pixels of the last image decoded from it
+pub struct Handle {
+	data: Vec<u8>,
+	num_images: usize,
+	pixels: Vec<u8>
+}
+
+/// Copies `len` bytes of file data and reads its offset table.
+/// Returns null if `data` is null or the offset table can't be read.
+///
+/// # Safety
+///
+/// `data` must point to at least `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pdl_open(data: *const u8, len: usize) -> *mut Handle {
+	if data.is_null() {
+		return ptr::null_mut();
+	}
+	let data = unsafe { slice::from_raw_parts(data, len) }.to_vec();
+	match read_offset_table(&data, Endian::default()) {
+		Ok(image_offsets) => Box::into_raw(Box::new(Handle {
+			num_images: image_offsets.len(),
+			data,
+			pixels: Vec::new()
+		})),
+		Err(_) => ptr::null_mut()
+	}
+}
+
+/// Number of images in the file, or 0 if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `pdl_open` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pdl_image_count(handle: *const Handle) -> usize {
+	match unsafe { handle.as_ref() } {
+		Some(handle) => handle.num_images,
+		None => 0
+	}
+}
+
+/// Decodes the image at `index` into a spritesheet with one row per palette, as RGBA8 pixels row by row.
+/// On success, `out_rgba` points to `out_w * out_h * 4` bytes owned by the handle, which stay valid until
+/// the next call to `pdl_decode_image` or `pdl_free`. Returns `PDL_OK` or one of the negative `PDL_ERR_*` codes.
+///
+/// # Safety
+///
+/// `handle` must be a pointer returned by `pdl_open` that hasn't been freed, and the out pointers must be
+/// valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pdl_decode_image(handle: *mut Handle, index: usize, out_rgba: *mut *const u8, out_w: *mut u32, out_h: *mut u32) -> i32 {
+	let Some(handle) = (unsafe { handle.as_mut() }) else { return PDL_ERR_NULL_POINTER };
+	if out_rgba.is_null() || out_w.is_null() || out_h.is_null() {
+		return PDL_ERR_NULL_POINTER;
+	}
+	let spritesheet = match build_spritesheet(&handle.data, index) {
+		Ok(spritesheet) => spritesheet,
+		Err(DecodeError::ImageIndexOutOfRange { .. }) => return PDL_ERR_INDEX_OUT_OF_RANGE,
+		Err(_) => return PDL_ERR_DECODE
+	};
+	let (width, height) = spritesheet.dimensions();
+	handle.pixels = spritesheet.into_raw();
+	unsafe {
+		*out_rgba = handle.pixels.as_ptr();
+		*out_w = width;
+		*out_h = height;
+	}
+	PDL_OK
+}
+
+/// Frees a handle returned by `pdl_open`, along with any pixels it holds. Does nothing if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `pdl_open` that hasn't already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pdl_free(handle: *mut Handle) {
+	if !handle.is_null() {
+		drop(unsafe { Box::from_raw(handle) });
+	}
+}
@@ -0,0 +1,45 @@
+use crate::{ ImageDef, PixelDataType };
+
+// a page showing every spritesheet next to its header values, for browsing an extracted folder.
+// images are linked by file name, so the page only works alongside them
+pub fn make_preview_page(title: &str, images: &[(usize, String, ImageDef)]) -> String {
+	let mut html = String::new();
+	html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+	html.push_str(&format!("<title>{}</title>\n", escape(title)));
+	html.push_str("<style>\n");
+	html.push_str("body { font-family: sans-serif; background: #303030; color: #e0e0e0; }\n");
+	html.push_str("section { display: flex; gap: 16px; margin: 16px 0; }\n");
+	html.push_str("img { image-rendering: pixelated; background: repeating-conic-gradient(#505050 0 25%, #404040 0 50%) 0 0 / 16px 16px; }\n");
+	html.push_str("th { text-align: left; padding-right: 12px; }\n");
+	html.push_str("</style>\n</head>\n<body>\n");
+	html.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+
+	for (index, file, def) in images {
+		let color = match def.pixel_data_type {
+			PixelDataType::Bpp(bpp) => format!("{} bpp", bpp),
+			PixelDataType::Direct => "direct color".to_string()
+		};
+		let rows = [
+			("compression", format!("{:?}", def.compression)),
+			("color", color),
+			("palettes", def.num_palettes.to_string()),
+			("sprite size", format!("{}x{}", def.sprite_width_px, def.sprite_height_px)),
+			("sprites", def.num_sprites.to_string()),
+			("subimage size", format!("{}x{} sprites", def.image_width, def.image_height)),
+			("subimages", def.num_subimages.to_string())
+		];
+		html.push_str(&format!("<section id=\"image-{}\">\n<div>\n<h2>Image {}</h2>\n<table>\n", index, index));
+		for (label, value) in rows {
+			html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, escape(&value)));
+		}
+		html.push_str("</table>\n</div>\n");
+		html.push_str(&format!("<img src=\"{}\" alt=\"image {}\">\n</section>\n", escape(file), index));
+	}
+
+	html.push_str("</body>\n</html>\n");
+	html
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
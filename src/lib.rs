@@ -0,0 +1,1669 @@
+use std::borrow::Cow;
+use bytes::{ Buf, BufMut, TryGetError };
+use image::{ GenericImage, GrayAlphaImage, GrayImage, ImageBuffer, Luma, LumaA, Pixel, Rgba, RgbaImage };
+use log::warn;
+use rayon::prelude::*;
+use serde::{ Serialize, Deserialize };
+use thiserror::Error;
+use encode::EncodeError;
+
+pub mod contact_sheet;
+pub mod encode;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod html;
+pub mod palette;
+pub mod quantize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// Format: https://gist.github.com/GMMan/a467961057d1e9fb08a2bbfd553180d6
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum CompressionType {
+	None,
+	Bytewise,
+	Wordwise
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PixelDataType {
+	Bpp(usize),
+	Direct
+}
+
+// bit layout of the 16-bit colors in palettes and direct color pixel data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ColorFormat {
+	#[default]
+	Rgb565,
+	Rgb555,
+	// the top bit is a one-bit alpha
+	Argb1555
+}
+
+// how 5- and 6-bit channels are widened to 8 bits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorExpansion {
+	// copy the high bits into the low bits, so 0 maps to 0 and the max maps to 255 exactly
+	#[default]
+	BitReplication,
+	// scale by 255 / max, truncating
+	Linear
+}
+
+impl ColorExpansion {
+	fn expand(self, value: u16, bits: u32) -> u8 {
+		match self {
+			ColorExpansion::BitReplication => ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8,
+			ColorExpansion::Linear => (value * 255 / ((1 << bits) - 1)) as u8
+		}
+	}
+}
+
+// the order indexed pixels are stored in within a sprite
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TileOrder {
+	// row by row across the whole sprite
+	#[default]
+	Linear,
+	// row by row within 8x8 tiles, with the tiles themselves row by row, as on the GBA and NDS
+	Tiled8x8
+}
+
+impl TileOrder {
+	// where the i-th stored pixel goes in a sprite this many pixels wide
+	pub fn position(self, i: usize, width: usize) -> (usize, usize) {
+		match self {
+			TileOrder::Linear => (i % width, i / width),
+			TileOrder::Tiled8x8 => {
+				let tile = i / 64;
+				let tiles_per_row = width.div_ceil(8);
+				((tile % tiles_per_row) * 8 + i % 8, (tile / tiles_per_row) * 8 + (i % 64) / 8)
+			}
+		}
+	}
+}
+
+impl ColorFormat {
+	pub fn parse(self, value: u16, expansion: ColorExpansion) -> Rgba<u8> {
+		match self {
+			ColorFormat::Rgb565 => parse_rgb565(value, expansion),
+			ColorFormat::Rgb555 => parse_rgb555(value, expansion),
+			ColorFormat::Argb1555 => {
+				let mut color = parse_rgb555(value, expansion);
+				color[3] = if value & 0x8000 != 0 { 255 } else { 0 };
+				color
+			}
+		}
+	}
+}
+
+// byte order of the offset table, header fields, colors and compressed sprite offsets. known files are
+// all little-endian
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Endian {
+	#[default]
+	Little,
+	Big
+}
+
+impl Endian {
+	fn get_u16(self, buf: &mut &[u8]) -> u16 {
+		match self {
+			Endian::Little => buf.get_u16_le(),
+			Endian::Big => buf.get_u16()
+		}
+	}
+
+	fn get_u32(self, buf: &mut &[u8]) -> u32 {
+		match self {
+			Endian::Little => buf.get_u32_le(),
+			Endian::Big => buf.get_u32()
+		}
+	}
+
+	fn try_get_u32(self, buf: &mut &[u8]) -> Result<u32, TryGetError> {
+		match self {
+			Endian::Little => buf.try_get_u32_le(),
+			Endian::Big => buf.try_get_u32()
+		}
+	}
+
+	pub(crate) fn put_u16(self, buf: &mut Vec<u8>, value: u16) {
+		match self {
+			Endian::Little => buf.put_u16_le(value),
+			Endian::Big => buf.put_u16(value)
+		}
+	}
+
+	pub(crate) fn put_u32(self, buf: &mut Vec<u8>, value: u32) {
+		match self {
+			Endian::Little => buf.put_u32_le(value),
+			Endian::Big => buf.put_u32(value)
+		}
+	}
+}
+
+pub const IMAGE_DEF_SIZE: usize = 24;
+
+// bits of the flags byte in an image header
+pub const FLAG_TRANSPARENCY: u8 = 0b00000100;
+pub const FLAG_COMPRESS_BYTEWISE: u8 = 0b00100000;
+pub const FLAG_COMPRESS_WORDWISE: u8 = 0b01000000;
+pub const FLAG_ENCRYPTED: u8 = 0b10000000;
+
+// splits a flags byte into has_transparency, compression and is_encrypted. a sprite can only be
+// compressed one way, so setting both compression bits is an error
+pub fn parse_flags(flags: u8) -> Result<(bool, CompressionType, bool), DecodeError> {
+	let has_transparency = (flags & FLAG_TRANSPARENCY) > 0;
+	let compression = match (flags & FLAG_COMPRESS_BYTEWISE > 0, flags & FLAG_COMPRESS_WORDWISE > 0) {
+		(true, true) => return Err(DecodeError::ConflictingCompressionFlags { flags }),
+		(true, false) => CompressionType::Bytewise,
+		(false, true) => CompressionType::Wordwise,
+		(false, false) => CompressionType::None
+	};
+	let is_encrypted = (flags & FLAG_ENCRYPTED) > 0;
+	Ok((has_transparency, compression, is_encrypted))
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ImageDef {
+	pub data_length: usize,
+	pub has_transparency: bool,
+	pub is_encrypted: bool,
+	pub compression: CompressionType,
+	pub pixel_data_type: PixelDataType,
+	pub num_sprites: usize,
+	pub sprite_width_px: usize,
+	pub sprite_height_px: usize,
+	pub offset_x: i8,
+	pub offset_y: i8,
+	pub image_width: usize,
+	pub image_height: usize,
+	// always 17 in known files
+	#[serde(default = "default_unknown")]
+	pub unknown: u8,
+	pub num_palettes: usize,
+	// a palette index for indexed images, but the raw RGB565 color value for direct color images
+	pub transparent_color_index: u16,
+	pub palette_data_offset: usize,
+	pub pixel_data_offset: usize,
+	// always 0 in known files
+	#[serde(default)]
+	pub padding: u16,
+	pub num_subimages: usize
+}
+
+pub const DEFAULT_UNKNOWN: u8 = 17;
+
+fn default_unknown() -> u8 {
+	DEFAULT_UNKNOWN
+}
+
+impl ImageDef {
+	// palette index that should be drawn transparent, if any
+	pub fn transparent_index(&self) -> Option<usize> {
+		match self.pixel_data_type {
+			PixelDataType::Bpp(_) if self.has_transparency => Some(self.transparent_color_index as usize),
+			_ => None
+		}
+	}
+
+	// size of a single sprite's pixel data before compression; with row_aligned, every row of an
+	// indexed sprite is padded to a whole number of bytes
+	pub fn bytes_per_sprite(&self, row_aligned: bool) -> usize {
+		if let PixelDataType::Bpp(bpp) = self.pixel_data_type {
+			if row_aligned {
+				(self.sprite_width_px * bpp).div_ceil(8) * self.sprite_height_px
+			} else {
+				(self.sprite_width_px * self.sprite_height_px * bpp).div_ceil(8)
+			}
+		} else {
+			self.sprite_width_px * self.sprite_height_px * 2
+		}
+	}
+
+	// RGB565 color value that should be drawn transparent, if any
+	pub fn transparent_color(&self) -> Option<u16> {
+		match self.pixel_data_type {
+			PixelDataType::Direct if self.has_transparency => Some(self.transparent_color_index),
+			_ => None
+		}
+	}
+
+	// header fields that disagree about whether the image has palettes, or how big they are
+	pub fn palette_mismatches(&self) -> Vec<String> {
+		let mut mismatches = Vec::new();
+		let palette_bytes = self.pixel_data_offset.saturating_sub(self.palette_data_offset);
+		match self.pixel_data_type {
+			PixelDataType::Direct if self.num_palettes != 0 => {
+				mismatches.push(format!("direct color image has num_palettes {}, expected 0", self.num_palettes));
+			},
+			PixelDataType::Bpp(bpp) => {
+				let needed = self.num_palettes * 2usize.pow(bpp as u32) * 2;
+				if palette_bytes < needed {
+					mismatches.push(format!("{} palettes at {} bpp need {} bytes, but the palette region is {}", self.num_palettes, bpp, needed, palette_bytes));
+				}
+			},
+			_ => {}
+		}
+		mismatches
+	}
+}
+
+pub const DEFAULT_DECRYPTION_KEY: u8 = 0x53;
+
+pub struct DecodeOptions {
+	// place subimages at offset_x/offset_y when compositing the spritesheet; every subimage in every
+	// palette row gets its own padded cell, so rows and columns stay aligned
+	pub apply_offsets: bool,
+	// XOR key for encrypted pixel data
+	pub decryption_key: u8,
+	// draw palette indices past the end of the palette as magenta instead of transparent, to find them
+	pub highlight_invalid_indices: bool,
+	// how to read palette colors and direct color pixels
+	pub color_format: ColorFormat,
+	pub color_expansion: ColorExpansion,
+	// indexed sprite rows start on a byte boundary, instead of the whole sprite being one bit stream
+	pub row_aligned: bool,
+	// how indexed pixels are laid out within a sprite
+	pub tile_order: TileOrder,
+	// replace the header's transparency for indexed and direct color images, for files where it's wrong
+	pub transparent_index: Option<usize>,
+	pub transparent_color: Option<[u8; 3]>,
+	// fail on sprites whose data doesn't hold exactly one pixel per chunk, or that use palette indices
+	// past the end of the palette, instead of warning
+	pub strict: bool,
+	// replace the header's sprite size before decoding, for files where it's corrupted
+	pub sprite_width: Option<usize>,
+	pub sprite_height: Option<usize>,
+	// wrap each palette's subimages into a grid this many columns wide, instead of a single row
+	pub columns: Option<usize>,
+	// transparent pixels between sprites, subimages and palette rows in spritesheets
+	pub gutter: usize,
+	// byte order of the offset table, header fields, 16-bit colors and compressed sprite offsets
+	pub endian: Endian
+}
+
+impl Default for DecodeOptions {
+	fn default() -> Self {
+		DecodeOptions {
+			apply_offsets: false,
+			decryption_key: DEFAULT_DECRYPTION_KEY,
+			highlight_invalid_indices: false,
+			color_format: ColorFormat::Rgb565,
+			color_expansion: ColorExpansion::BitReplication,
+			row_aligned: false,
+			tile_order: TileOrder::Linear,
+			transparent_index: None,
+			transparent_color: None,
+			strict: false,
+			sprite_width: None,
+			sprite_height: None,
+			columns: None,
+			gutter: 0,
+			endian: Endian::Little
+		}
+	}
+}
+
+impl DecodeOptions {
+	// palette index to draw transparent, from the override or the header
+	pub fn transparent_index(&self, def: &ImageDef) -> Option<usize> {
+		match def.pixel_data_type {
+			PixelDataType::Bpp(_) => self.transparent_index.or(def.transparent_index()),
+			PixelDataType::Direct => None
+		}
+	}
+}
+
+pub struct DecodedImage {
+	pub def: ImageDef,
+	pub palettes: Vec<Vec<Rgba<u8>>>,
+	pub pixel_data_per_sprite: Vec<Vec<u8>>
+}
+
+pub struct ParsedImage {
+	pub def: ImageDef,
+	pub spritesheet: RgbaImage
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+	#[error("unexpected end of data at offset {offset} (needed {needed} bytes)")]
+	UnexpectedEof { offset: usize, needed: usize },
+	#[error("input file too small to contain an offset table ({len} bytes)")]
+	FileTooSmall { len: usize },
+	#[error("first image offset {offset} doesn't point into the file ({len} bytes)")]
+	BadOffsetTable { offset: usize, len: usize },
+	#[error("first image offset {offset} isn't a whole number of 4-byte offset table entries")]
+	MisalignedOffsetTable { offset: usize },
+	#[error("dimensions {width}x{height} have no area")]
+	BadDimensions { width: usize, height: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum DecompressError {
+	#[error("compressed data is truncated at offset {offset} (needed {needed} bytes, got {available})")]
+	Truncated { offset: usize, needed: usize, available: usize },
+	#[error("repeat run at offset {offset} would decompress to {len} bytes, but the sprite is only {max_len}")]
+	RunTooLong { offset: usize, len: usize, max_len: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+	#[error("no input path given")]
+	MissingInput,
+	#[error("no output path given")]
+	MissingOutput,
+	#[error("image header is truncated ({len} of {IMAGE_DEF_SIZE} bytes)")]
+	TruncatedHeader { len: usize },
+	#[error("offset {index} is past the end of the data ({len} bytes)")]
+	OffsetOutOfBounds { index: usize, len: usize },
+	#[error("data range {start}..{end} ends before it starts")]
+	BackwardsRange { start: usize, end: usize },
+	#[error("flags {flags:#010b} set both bytewise and wordwise compression")]
+	ConflictingCompressionFlags { flags: u8 },
+	#[error("image index {index} is out of range (file has {num_images} images)")]
+	ImageIndexOutOfRange { index: usize, num_images: usize },
+	#[error("color index {index} is out of range for a palette of {len} colors")]
+	PaletteIndexOutOfRange { index: usize, len: usize },
+	#[error("sprite has {got} pixels, expected {expected}")]
+	SpriteSizeMismatch { expected: usize, got: usize },
+	#[error("unable to copy sprite into image: {0}")]
+	SubimageCopy(#[source] image::ImageError),
+	#[error("failed to parse file: {0}")]
+	Parse(#[from] ParseError),
+	#[error("failed to decompress sprite: {0}")]
+	Decompress(#[from] DecompressError),
+	#[error(transparent)]
+	Io(#[from] std::io::Error)
+}
+
+// decodes images one at a time, so callers can stop early without decoding the whole file
+pub struct ImageReader<'a> {
+	data: &'a [u8],
+	image_offsets: Vec<u32>,
+	next_index: usize,
+	options: DecodeOptions
+}
+
+impl<'a> ImageReader<'a> {
+	pub fn new(data: &'a [u8]) -> Result<Self, DecodeError> {
+		Self::with_options(data, DecodeOptions::default())
+	}
+
+	pub fn with_options(data: &'a [u8], options: DecodeOptions) -> Result<Self, DecodeError> {
+		let image_offsets = read_offset_table(data, options.endian)?;
+		Ok(ImageReader { data, image_offsets, next_index: 0, options })
+	}
+
+	pub fn num_images(&self) -> usize {
+		self.image_offsets.len()
+	}
+
+	fn parse_image(&self, image_offset: u32) -> Result<ParsedImage, DecodeError> {
+		let image = decode_image(self.data, image_offset as usize, &self.options)?;
+		let spritesheet = make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &self.options)?;
+		Ok(ParsedImage { def: image.def, spritesheet })
+	}
+}
+
+impl Iterator for ImageReader<'_> {
+	type Item = Result<ParsedImage, DecodeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let image_offset = *self.image_offsets.get(self.next_index)?;
+		self.next_index += 1;
+		Some(self.parse_image(image_offset))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.image_offsets.len() - self.next_index;
+		(remaining, Some(remaining))
+	}
+}
+
+impl ExactSizeIterator for ImageReader<'_> {}
+
+pub fn parse_file(data: &[u8]) -> Result<Vec<ParsedImage>, DecodeError> {
+	ImageReader::new(data)?.collect()
+}
+
+pub fn decode(data: &[u8]) -> Result<Vec<DecodedImage>, DecodeError> {
+	let image_offsets = read_offset_table(data, Endian::default())?;
+	image_offsets.iter().map(|image_offset| decode_image(data, *image_offset as usize, &DecodeOptions::default())).collect()
+}
+
+pub fn build_spritesheet(data: &[u8], index: usize) -> Result<RgbaImage, DecodeError> {
+	let image_offsets = read_offset_table(data, Endian::default())?;
+	let image_offset = image_offsets.get(index).ok_or(DecodeError::ImageIndexOutOfRange {
+		index,
+		num_images: image_offsets.len()
+	})?;
+	let image = decode_image(data, *image_offset as usize, &DecodeOptions::default())?;
+	make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &DecodeOptions::default())
+}
+
+// offsets of every image in the file, read from the table at its start without decoding anything,
+// so callers can pick which images to decode
+pub fn read_offset_table(data: &[u8], endian: Endian) -> Result<Vec<u32>, DecodeError> {
+	let mut buffer = data;
+	let eof = |err: TryGetError| ParseError::UnexpectedEof {
+		offset: data.len() - err.available,
+		needed: err.requested
+	};
+
+	if data.len() < 4 {
+		return Err(ParseError::FileTooSmall { len: data.len() }.into());
+	}
+	let first_image_offset = endian.try_get_u32(&mut buffer).map_err(eof)?;
+	if first_image_offset == 0 || first_image_offset as usize >= data.len() {
+		return Err(ParseError::BadOffsetTable { offset: first_image_offset as usize, len: data.len() }.into());
+	}
+	// the table ends where the first image starts, so it must hold at least that one offset
+	if first_image_offset < 4 || !first_image_offset.is_multiple_of(4) {
+		return Err(ParseError::MisalignedOffsetTable { offset: first_image_offset as usize }.into());
+	}
+	let mut image_offsets: Vec<u32> = vec![first_image_offset];
+	let mut current_offset = 4;
+	while current_offset < first_image_offset {
+		let image_offset = endian.try_get_u32(&mut buffer).map_err(eof)?;
+		image_offsets.push(image_offset);
+		current_offset += 4;
+	}
+
+	Ok(image_offsets)
+}
+
+pub fn decode_image(data: &[u8], image_offset: usize, options: &DecodeOptions) -> Result<DecodedImage, DecodeError> {
+	let mut def = read_image_def(data, image_offset, options.endian)?;
+	if let Some(width) = options.sprite_width {
+		def.sprite_width_px = width;
+	}
+	if let Some(height) = options.sprite_height {
+		def.sprite_height_px = height;
+	}
+	if def.sprite_width_px * def.sprite_height_px == 0 {
+		return Err(ParseError::BadDimensions { width: def.sprite_width_px, height: def.sprite_height_px }.into());
+	}
+	for mismatch in def.palette_mismatches() {
+		warn!("{}", mismatch);
+	}
+
+	// calc data offsets
+	let palette_data_index = image_offset + def.palette_data_offset;
+	let pixel_data_index = image_offset + def.pixel_data_offset;
+	let end_index = image_offset + def.data_length;
+
+	// get color palettes
+	let mut palettes = Vec::new();
+	if let PixelDataType::Bpp(bpp) = def.pixel_data_type {
+		let palette_data = get_range(data, palette_data_index, pixel_data_index)?;
+		let colors_per_palette = 2usize.pow(bpp as u32);
+		palettes = get_palettes(palette_data, colors_per_palette, def.num_palettes, options.color_format, options.color_expansion, options.endian);
+
+		// without a palette there would be nothing to draw, so fall back to shades of gray
+		if palettes.is_empty() {
+			warn!("indexed image has no palettes, drawing it in grayscale");
+			palettes.push(grayscale_palette(colors_per_palette));
+		}
+	}
+
+	// get pixel data for each sprite
+	let pixel_data = get_range(data, pixel_data_index, end_index)?;
+	let pixel_data_per_sprite = get_pixel_data_per_sprite(pixel_data, &def, options)?;
+
+	Ok(DecodedImage { def, palettes, pixel_data_per_sprite })
+}
+
+// reads just the header of the image at image_offset, without touching its palettes or pixel data
+pub fn read_image_def(data: &[u8], image_offset: usize, endian: Endian) -> Result<ImageDef, DecodeError> {
+	parse_image_def(get_range(data, image_offset, data.len())?, endian)
+}
+
+pub fn parse_image_def(mut bytes: &[u8], endian: Endian) -> Result<ImageDef, DecodeError> {
+	if bytes.len() < IMAGE_DEF_SIZE {
+		return Err(DecodeError::TruncatedHeader { len: bytes.len() });
+	}
+
+	let data_length = endian.get_u32(&mut bytes) as usize;
+
+	// read flags
+	let (has_transparency, compression, is_encrypted) = parse_flags(bytes.get_u8())?;
+
+	// determine bpp
+	let pixel_data_type = match bytes.get_u8() {
+		0 => PixelDataType::Bpp(1),
+		1 => PixelDataType::Bpp(2),
+		2 => PixelDataType::Bpp(4),
+		3 => PixelDataType::Bpp(8),
+		_ => PixelDataType::Direct
+	};
+
+	// read other properties
+	let num_sprites = endian.get_u16(&mut bytes) as usize;
+	let sprite_width_px = bytes.get_u8() as usize;
+	let sprite_height_px = bytes.get_u8() as usize;
+	let offset_x = bytes.get_i8();
+	let offset_y = bytes.get_i8();
+	let image_width = bytes.get_u8() as usize;
+	let image_height = bytes.get_u8() as usize;
+	let unknown = bytes.get_u8();
+	let num_palettes = bytes.get_u8() as usize;
+	let transparent_color_index = endian.get_u16(&mut bytes);
+	let palette_data_offset = endian.get_u16(&mut bytes) as usize;
+	let pixel_data_offset = endian.get_u16(&mut bytes) as usize;
+	let padding = endian.get_u16(&mut bytes);
+
+	// surface files that break the assumptions about these fields
+	if unknown != DEFAULT_UNKNOWN {
+		warn!("unknown header field is {}, expected {}", unknown, DEFAULT_UNKNOWN);
+	}
+	if padding != 0 {
+		warn!("header padding is {}, expected 0", padding);
+	}
+
+	// every pixel's position divides by the sprite width, so an empty sprite can't be drawn
+	if sprite_width_px * sprite_height_px == 0 {
+		return Err(ParseError::BadDimensions { width: sprite_width_px, height: sprite_height_px }.into());
+	}
+
+	// calc number of subimages
+	if image_width * image_height == 0 {
+		return Err(ParseError::BadDimensions { width: image_width, height: image_height }.into());
+	}
+	let sprites_per_subimage = image_width * image_height;
+	let mut num_subimages = num_sprites / sprites_per_subimage;
+	if !num_sprites.is_multiple_of(sprites_per_subimage) {
+		// pad the last subimage with transparent sprites rather than dropping the remainder
+		warn!("{} sprites don't fill {}x{} subimages evenly", num_sprites, image_width, image_height);
+		num_subimages += 1;
+	}
+
+	// return image def
+	Ok(ImageDef {
+		data_length,
+		has_transparency,
+		is_encrypted,
+		compression,
+		pixel_data_type,
+		num_sprites,
+		num_subimages,
+		sprite_width_px,
+		sprite_height_px,
+		offset_x,
+		offset_y,
+		image_width,
+		image_height,
+		unknown,
+		num_palettes,
+		transparent_color_index,
+		palette_data_offset,
+		pixel_data_offset,
+		padding
+	})
+}
+
+pub(crate) fn parse_rgb565(value: u16, expansion: ColorExpansion) -> Rgba<u8> {
+	let r = expansion.expand(value >> 11, 5);
+	let g = expansion.expand((value >> 5) & 0b111111, 6);
+	let b = expansion.expand(value & 0b11111, 5);
+	Rgba([r, g, b, 255])
+}
+
+pub(crate) fn parse_rgb555(value: u16, expansion: ColorExpansion) -> Rgba<u8> {
+	let r = expansion.expand((value >> 10) & 0b11111, 5);
+	let g = expansion.expand((value >> 5) & 0b11111, 5);
+	let b = expansion.expand(value & 0b11111, 5);
+	Rgba([r, g, b, 255])
+}
+
+pub fn get_palettes(bytes: &[u8], colors_per_palette: usize, num_palettes: usize, color_format: ColorFormat, expansion: ColorExpansion, endian: Endian) -> Vec<Vec<Rgba<u8>>> {
+	let mut buf = bytes;
+	let mut palettes = vec![Vec::new(); num_palettes];
+
+	// get all colors
+	let mut colors = Vec::new();
+	while buf.remaining() >= 2 {
+		let value = endian.get_u16(&mut buf);
+		let color = color_format.parse(value, expansion);
+		colors.push(color);
+	}
+
+	// assign colors to palettes
+	for (i, color) in colors.iter().enumerate() {
+		let palette_index = i / colors_per_palette;
+		if palette_index < palettes.len() {
+			palettes[palette_index].push(*color);
+		}
+	}
+
+	palettes
+}
+
+// evenly spaced from black to white
+fn grayscale_palette(num_colors: usize) -> Vec<Rgba<u8>> {
+	let max = num_colors.saturating_sub(1).max(1);
+	(0..num_colors).map(|c| {
+		let value = (c * 255 / max) as u8;
+		Rgba([value, value, value, 255])
+	}).collect()
+}
+
+// every slice of the file goes through here, so corrupt headers turn into errors instead of panics
+fn get_range(data: &[u8], start: usize, end: usize) -> Result<&[u8], DecodeError> {
+	if start > data.len() || end > data.len() {
+		return Err(DecodeError::OffsetOutOfBounds { index: start.max(end), len: data.len() });
+	}
+	data.get(start..end).ok_or(DecodeError::BackwardsRange { start, end })
+}
+
+fn get_pixel_data_per_sprite(data: &[u8], def: &ImageDef, options: &DecodeOptions) -> Result<Vec<Vec<u8>>, DecodeError> {
+	if let CompressionType::None = def.compression {
+		get_uncompressed_pixel_data(data, def, options)
+	} else {
+		get_compressed_pixel_data(data, def, options)
+	}
+}
+
+fn get_uncompressed_pixel_data(data: &[u8], def: &ImageDef, options: &DecodeOptions) -> Result<Vec<Vec<u8>>, DecodeError> {
+	// if uncompressed, each sprite has a fixed size
+	let bytes_per_sprite = def.bytes_per_sprite(options.row_aligned);
+
+	let mut pixel_data_per_sprite = Vec::new();
+	for j in 0..def.num_sprites {
+		let a = bytes_per_sprite * j;
+		let b = a + bytes_per_sprite;
+		let sprite_data = get_range(data, a, b)?;
+		let pixel_data = if def.is_encrypted {
+			decrypt_pixel_data(sprite_data, options.decryption_key)
+		} else {
+			sprite_data.to_vec()
+		};
+		pixel_data_per_sprite.push(pixel_data);
+	}
+	Ok(pixel_data_per_sprite)
+}
+
+fn get_compressed_pixel_data(data: &[u8], def: &ImageDef, options: &DecodeOptions) -> Result<Vec<Vec<u8>>, DecodeError> {
+	// if compressed, get offsets + lengths and use those to get pixel data per sprite
+	let mut pixel_data_per_sprite = Vec::new();
+	let mut buf = get_range(data, 0, def.num_sprites * 8)?;
+	for _ in 0..def.num_sprites {
+		let a = options.endian.get_u32(&mut buf) as usize;
+		let len = options.endian.get_u32(&mut buf) as usize;
+		let sprite_data = get_range(data, a, a + len)?;
+		let pixel_data = if def.is_encrypted {
+			decrypt_pixel_data(sprite_data, options.decryption_key)
+		} else {
+			sprite_data.to_vec()
+		};
+		pixel_data_per_sprite.push(pixel_data);
+	}
+	Ok(pixel_data_per_sprite)
+}
+
+// XOR is symmetric, so this also encrypts pixel data when encoding
+pub fn decrypt_pixel_data(data: &[u8], key: u8) -> Vec<u8> {
+	data.iter().map(|byte| byte ^ key).collect()
+}
+
+pub fn decompress_bytewise(bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+	let mut chunks = Vec::new();
+	let mut buf = bytes;
+	while buf.remaining() >= 1 {
+		let offset = bytes.len() - buf.remaining();
+		let control = buf.get_u8();
+		let top_bit = control >> 7;
+		let n = control & 0x7f;
+		// a literal run needs its n bytes, a repeat run needs the one byte it repeats
+		let needed = if top_bit == 1 { n as usize } else { 1 };
+		if buf.remaining() < needed {
+			return Err(DecompressError::Truncated { offset, needed, available: buf.remaining() });
+		}
+		if top_bit == 1 {
+			for _ in 0..n {
+				let value = buf.get_u8();
+				chunks.push(value);
+			}
+		} else {
+			let value = buf.get_u8();
+			for _ in 0..n {
+				chunks.push(value);
+			}
+		}
+	}
+	Ok(chunks)
+}
+
+// inverse of decompress_bytewise, using repeat runs for 2 or more equal bytes and literal runs otherwise.
+// both kinds of run are capped at 127 bytes, since the count shares the control byte with the top bit
+pub fn compress_bytewise(data: &[u8]) -> Vec<u8> {
+	let mut compressed = Vec::new();
+	let mut i = 0;
+	while i < data.len() {
+		let run = data[i..].iter().take(0x7f).take_while(|value| **value == data[i]).count();
+		if run >= 2 {
+			// repeat [value] n times
+			compressed.push(run as u8);
+			compressed.push(data[i]);
+			i += run;
+		} else {
+			// add next n bytes, up until the next repeated run
+			let mut n = 1;
+			while i + n < data.len() && n < 0x7f && !(i + n + 1 < data.len() && data[i + n] == data[i + n + 1]) {
+				n += 1;
+			}
+			compressed.push(0x80 | n as u8);
+			compressed.extend(&data[i..(i + n)]);
+			i += n;
+		}
+	}
+	compressed
+}
+
+// max_len bounds repeat runs, so a corrupt count can't allocate far more than the sprite needs
+fn decompress_wordwise(bytes: &[u8], max_len: usize) -> Result<Vec<u8>, DecompressError> {
+	let mut chunks = Vec::new();
+	let mut buf = bytes;
+	while buf.remaining() >= 4 {
+		let offset = bytes.len() - buf.remaining();
+		let control = buf.get_u32_le();
+		let top_bit = control >> 31;
+		let n = (control & 0x0fffffff) as usize;
+		// a literal run needs its n words, a repeat run needs the one word it repeats
+		let needed = if top_bit > 0 { n * 4 } else { 4 };
+		if buf.remaining() < needed {
+			return Err(DecompressError::Truncated { offset, needed, available: buf.remaining() });
+		}
+		if top_bit > 0 {
+			// add next n chunks
+			for _ in 0..n {
+				let value = buf.get_u32_le().to_le_bytes();
+				chunks.extend(value.iter());
+			}
+		} else {
+			// repeat [value] n times
+			if chunks.len() + n * 4 > max_len.next_multiple_of(4) {
+				return Err(DecompressError::RunTooLong { offset, len: n * 4, max_len });
+			}
+			let value = buf.get_u32_le().to_le_bytes();
+			for _ in 0..n {
+				chunks.extend(value.iter());
+			}
+		}
+	}
+	Ok(chunks)
+}
+
+// inverse of decompress_wordwise, using repeat runs for 2 or more equal words and literal runs otherwise.
+// control words are little endian like the decoder reads them, and words are copied through byte for byte
+pub fn compress_wordwise(data: &[u8]) -> Result<Vec<u8>, EncodeError> {
+	if !data.len().is_multiple_of(4) {
+		return Err(EncodeError::UnalignedWords(data.len()));
+	}
+	let words: Vec<&[u8]> = data.chunks(4).collect();
+
+	let mut compressed = Vec::new();
+	let mut i = 0;
+	while i < words.len() {
+		let run = words[i..].iter().take(0x0fffffff).take_while(|word| **word == words[i]).count();
+		if run >= 2 {
+			// repeat [value] n times
+			compressed.extend((run as u32).to_le_bytes());
+			compressed.extend(words[i]);
+			i += run;
+		} else {
+			// add next n words, up until the next repeated run
+			let mut n = 1;
+			while i + n < words.len() && n < 0x0fffffff && !(i + n + 1 < words.len() && words[i + n] == words[i + n + 1]) {
+				n += 1;
+			}
+			compressed.extend((0x80000000 | n as u32).to_le_bytes());
+			compressed.extend(&data[(i * 4)..((i + n) * 4)]);
+			i += n;
+		}
+	}
+	Ok(compressed)
+}
+
+// least-significant bit first, so byte_to_bits(0b0000_0110) is [0, 1, 1, 0, 0, 0, 0, 0]
+fn byte_to_bits(byte: u8) -> Vec<u8> {
+	let mut bits = Vec::new();
+	for i in 0..8 {
+		bits.push((byte >> i) & 1);
+	}
+	bits
+}
+
+// inverse of byte_to_bits for chunks of any length: the first bit is the least significant, so a
+// 2-bit chunk [1, 0] is 1 and [0, 1] is 2. bpp always divides 8, so chunks never straddle a byte
+fn bits_to_byte(bits: &[u8]) -> u8 {
+	let mut byte = 0;
+	for (i, bit) in bits.iter().enumerate() {
+		byte |= bit << i;
+	}
+	byte
+}
+
+// turns an entry of pixel_data_per_sprite into raw pixel data; uncompressed data is borrowed as-is
+pub fn decompress_sprite<'a>(data: &'a [u8], def: &ImageDef) -> Result<Cow<'a, [u8]>, DecompressError> {
+	Ok(match def.compression {
+		CompressionType::None => Cow::Borrowed(data),
+		CompressionType::Bytewise => Cow::Owned(decompress_bytewise(data)?),
+		// rows padded to whole bytes are never smaller than a packed sprite, so either layout fits
+		CompressionType::Wordwise => Cow::Owned(decompress_wordwise(data, def.bytes_per_sprite(true))?)
+	})
+}
+
+// palette index of every pixel in a decompressed sprite, in row order, for drawing with any palette
+pub fn decode_indices(bytes: &[u8], def: &ImageDef, bpp: usize, options: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+	let indices = unpack_indices(bytes, def, bpp, options)?;
+	warn_wrong_index_count(&indices, def);
+	Ok(indices)
+}
+
+// decode_indices without the warning, for decoding on other threads. warnings are logged from the
+// thread decoding the image, so they can be told apart from other images' warnings
+fn unpack_indices(bytes: &[u8], def: &ImageDef, bpp: usize, options: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+	let mut buf = bytes;
+
+	// add bits to end of stream in least-significant order
+	let mut bits = Vec::new();
+	if options.row_aligned {
+		// drop the padding bits at the end of each row
+		let bits_per_row = def.sprite_width_px * bpp;
+		for row in bytes.chunks(bits_per_row.div_ceil(8).max(1)) {
+			let row_bits: Vec<u8> = row.iter().flat_map(|byte| byte_to_bits(*byte)).collect();
+			bits.extend(&row_bits[..bits_per_row.min(row_bits.len())]);
+		}
+	} else {
+		while buf.remaining() >= 1 {
+			bits.extend(byte_to_bits(buf.get_u8()));
+		}
+	}
+
+	// the last byte of a sprite whose bits don't fill it is padded out, so drop the padding bits
+	// rather than reading them as an extra pixel
+	let expected_chunks = def.sprite_width_px * def.sprite_height_px;
+	if bytes.len() == def.bytes_per_sprite(options.row_aligned) {
+		bits.truncate(expected_chunks * bpp);
+	}
+
+	// divide bits into chunks of n bits, where n is bpp (bits per pixel)
+	let chunks = bits.chunks(bpp);
+	if chunks.len() != expected_chunks && options.strict {
+		return Err(DecodeError::SpriteSizeMismatch { expected: expected_chunks, got: chunks.len() });
+	}
+	Ok(chunks.map(bits_to_byte).collect())
+}
+
+fn warn_wrong_index_count(indices: &[u8], def: &ImageDef) {
+	let expected_chunks = def.sprite_width_px * def.sprite_height_px;
+	if indices.len() != expected_chunks {
+		warn!("expected {} chunks, got {}", expected_chunks, indices.len());
+	}
+}
+
+// returns the sprite along with the number of pixels whose palette index was out of range
+fn make_indexed_sprite(indices: &[u8], def: &ImageDef, palette: &[Rgba<u8>], options: &DecodeOptions) -> Result<(RgbaImage, usize), DecodeError> {
+	let mut img = RgbaImage::new(def.sprite_width_px as u32, def.sprite_height_px as u32);
+
+	// draw each pixel from its palette index, flagging indices past the end of the palette
+	let invalid_color = if options.highlight_invalid_indices { Rgba([255, 0, 255, 255]) } else { Rgba([0, 0, 0, 0]) };
+	let mut num_invalid = 0;
+	let transparent_index = options.transparent_index(def);
+	for (i, index) in indices.iter().enumerate() {
+		let index = *index as usize;
+		let (x, y) = options.tile_order.position(i, def.sprite_width_px);
+		let color = if transparent_index == Some(index) {
+			Rgba([0, 0, 0, 0])
+		} else if let Some(color) = palette.get(index) {
+			*color
+		} else if options.strict {
+			return Err(DecodeError::PaletteIndexOutOfRange { index, len: palette.len() });
+		} else {
+			num_invalid += 1;
+			invalid_color
+		};
+		if x < def.sprite_width_px && y < def.sprite_height_px {
+			img.put_pixel(x as u32, y as u32, color);
+		}
+	}
+
+	Ok((img, num_invalid))
+}
+
+fn make_direct_sprite(bytes: &[u8], def: &ImageDef, options: &DecodeOptions) -> RgbaImage {
+	let mut img = RgbaImage::new(def.sprite_width_px as u32, def.sprite_height_px as u32);
+	let mut buf = bytes;
+	let num_pixels = def.sprite_width_px * def.sprite_height_px;
+	let mut i = 0;
+	while buf.remaining() >= 2 && i < num_pixels {
+		let x = i % def.sprite_width_px;
+		let y = i / def.sprite_width_px;
+		let value = options.endian.get_u16(&mut buf);
+		let mut color = options.color_format.parse(value, options.color_expansion);
+		let is_transparent = match options.transparent_color {
+			Some(rgb) => color.0[..3] == rgb,
+			None => def.transparent_color() == Some(value)
+		};
+		if is_transparent {
+			color = Rgba([0, 0, 0, 0]);
+		}
+		img.put_pixel(x as u32, y as u32, color);
+		i += 1;
+	}
+	img
+}
+
+// make_direct_sprite reads whole pixels until the data or the sprite runs out
+fn warn_wrong_pixel_count(len: usize, def: &ImageDef) {
+	let num_pixels = def.sprite_width_px * def.sprite_height_px;
+	let read = num_pixels.min(len / 2);
+	let trailing = len - read * 2;
+	if read < num_pixels || trailing == 1 {
+		warn!("expected {} pixels, got {} ({} trailing bytes)", num_pixels, read, trailing);
+	}
+}
+
+// lays sprites out in an image_width x image_height grid, with gutter pixels of space between them
+fn make_subimage<P: Pixel<Subpixel = u8>>(sprites: &[ImageBuffer<P, Vec<u8>>], def: &ImageDef, gutter: usize) -> Result<ImageBuffer<P, Vec<u8>>, DecodeError> {
+	let (width, height) = subimage_size(def, gutter);
+	// headers are checked when they're read, but sprite size overrides and hand-built defs aren't
+	if width == 0 || height == 0 {
+		return Err(ParseError::BadDimensions { width, height }.into());
+	}
+	let mut img = ImageBuffer::new(width as u32, height as u32);
+	for (i, sprite) in sprites.iter().enumerate() {
+		let (x, y) = sprite_origin(def, i, gutter);
+		img.copy_from(sprite, x as u32, y as u32).map_err(DecodeError::SubimageCopy)?;
+	}
+	Ok(img)
+}
+
+fn subimage_size(def: &ImageDef, gutter: usize) -> (usize, usize) {
+	(
+		def.image_width * def.sprite_width_px + def.image_width.saturating_sub(1) * gutter,
+		def.image_height * def.sprite_height_px + def.image_height.saturating_sub(1) * gutter
+	)
+}
+
+// where the k-th sprite of a subimage goes within it
+fn sprite_origin(def: &ImageDef, k: usize, gutter: usize) -> (usize, usize) {
+	(
+		(k % def.image_width) * (def.sprite_width_px + gutter),
+		(k / def.image_width) * (def.sprite_height_px + gutter)
+	)
+}
+
+// where each subimage sits in the spritesheet: one cell per subimage per palette row
+struct SheetLayout {
+	shift_x: usize,
+	shift_y: usize,
+	cell_width: usize,
+	cell_height: usize,
+	columns: usize,
+	// space between sprites, between subimages and between palette rows
+	gutter: usize,
+	// size of the grid of subimages drawn with a single palette
+	band_width: usize,
+	band_height: usize
+}
+
+impl SheetLayout {
+	// where subimage j drawn with palette row i goes, wrapping into a new row of cells every columns subimages
+	fn origin(&self, i: usize, j: usize) -> (usize, usize) {
+		(
+			(j % self.columns) * self.cell_width + self.shift_x,
+			i * (self.band_height + self.gutter) + (j / self.columns) * self.cell_height + self.shift_y
+		)
+	}
+
+	fn height(&self, num_rows: usize) -> usize {
+		num_rows * self.band_height + num_rows.saturating_sub(1) * self.gutter
+	}
+}
+
+fn sheet_layout(def: &ImageDef, options: &DecodeOptions) -> SheetLayout {
+	// optionally place each subimage at the header offsets within its own cell, growing the cell by
+	// the size of the offset so negative offsets fit too
+	let (shift_x, shift_y, pad_x, pad_y) = if options.apply_offsets {
+		(
+			def.offset_x.max(0) as usize,
+			def.offset_y.max(0) as usize,
+			def.offset_x.unsigned_abs() as usize,
+			def.offset_y.unsigned_abs() as usize
+		)
+	} else {
+		(0, 0, 0, 0)
+	};
+	// the gutter is part of each cell, so it falls between them
+	let gutter = options.gutter;
+	let (subimage_width, subimage_height) = subimage_size(def, gutter);
+	let cell_width = subimage_width + pad_x + gutter;
+	let cell_height = subimage_height + pad_y + gutter;
+
+	// a single row of subimages unless asked to wrap
+	let columns = options.columns.unwrap_or(def.num_subimages).clamp(1, def.num_subimages.max(1));
+	let rows = def.num_subimages.div_ceil(columns);
+	SheetLayout {
+		shift_x,
+		shift_y,
+		cell_width,
+		cell_height,
+		columns,
+		gutter,
+		band_width: (columns * cell_width).saturating_sub(gutter),
+		band_height: (rows * cell_height).saturating_sub(gutter)
+	}
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Rect {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32
+}
+
+impl Rect {
+	pub fn scale(&mut self, factor: u32) {
+		self.x *= factor;
+		self.y *= factor;
+		self.width *= factor;
+		self.height *= factor;
+	}
+}
+
+#[derive(Serialize)]
+pub struct AtlasSprite {
+	pub sprite: usize,
+	pub rect: Rect
+}
+
+#[derive(Serialize)]
+pub struct AtlasFrame {
+	pub palette: usize,
+	pub subimage: usize,
+	pub rect: Rect,
+	pub sprites: Vec<AtlasSprite>
+}
+
+// describes where every subimage and sprite ends up in the sheet from make_spritesheet
+#[derive(Serialize)]
+pub struct Atlas {
+	pub width: u32,
+	pub height: u32,
+	pub frames: Vec<AtlasFrame>
+}
+
+impl Atlas {
+	// matches a spritesheet that's been scaled up by a whole number
+	pub fn scale(&mut self, factor: u32) {
+		self.width *= factor;
+		self.height *= factor;
+		for frame in &mut self.frames {
+			frame.rect.scale(factor);
+			for sprite in &mut frame.sprites {
+				sprite.rect.scale(factor);
+			}
+		}
+	}
+}
+
+// the smallest rect holding every pixel that isn't fully transparent, or None if there are none
+pub fn trim_bounds(img: &RgbaImage) -> Option<Rect> {
+	let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+	for (x, y, pixel) in img.enumerate_pixels() {
+		if pixel[3] != 0 {
+			min_x = min_x.min(x);
+			min_y = min_y.min(y);
+			max_x = max_x.max(x);
+			max_y = max_y.max(y);
+		}
+	}
+	if min_x == u32::MAX {
+		return None;
+	}
+	Some(Rect {
+		x: min_x,
+		y: min_y,
+		width: max_x - min_x + 1,
+		height: max_y - min_y + 1
+	})
+}
+
+// where a sprite sits within its subimage, as a grid cell and in pixels, for putting split sprites back together
+#[derive(Serialize)]
+pub struct SpritePlacement {
+	pub sprite: usize,
+	pub subimage: usize,
+	pub column: usize,
+	pub row: usize,
+	pub x: u32,
+	pub y: u32
+}
+
+pub fn sprite_placements(def: &ImageDef) -> Vec<SpritePlacement> {
+	let sprites_per_subimage = def.image_width * def.image_height;
+	(0..def.num_sprites).map(|sprite| {
+		let cell = sprite % sprites_per_subimage;
+		let column = cell % def.image_width;
+		let row = cell / def.image_width;
+		SpritePlacement {
+			sprite,
+			subimage: sprite / sprites_per_subimage,
+			column,
+			row,
+			x: (column * def.sprite_width_px) as u32,
+			y: (row * def.sprite_height_px) as u32
+		}
+	}).collect()
+}
+
+pub fn make_atlas(def: &ImageDef, palettes: &[Vec<Rgba<u8>>], options: &DecodeOptions) -> Atlas {
+	let num_rows = palette_rows(def, palettes).len();
+	let layout = sheet_layout(def, options);
+	let sprites_per_subimage = def.image_width * def.image_height;
+	let mut frames = Vec::new();
+	for i in 0..num_rows {
+		for j in 0..def.num_subimages {
+			let (x, y) = layout.origin(i, j);
+			let sprites = (0..sprites_per_subimage)
+				.map(|k| (k, j * sprites_per_subimage + k))
+				.filter(|(_, sprite)| *sprite < def.num_sprites)
+				.map(|(k, sprite)| {
+					let (sprite_x, sprite_y) = sprite_origin(def, k, layout.gutter);
+					AtlasSprite {
+						sprite,
+						rect: Rect {
+							x: (x + sprite_x) as u32,
+							y: (y + sprite_y) as u32,
+							width: def.sprite_width_px as u32,
+							height: def.sprite_height_px as u32
+						}
+					}
+				}).collect();
+			let (width, height) = subimage_size(def, layout.gutter);
+			frames.push(AtlasFrame {
+				palette: i,
+				subimage: j,
+				rect: Rect {
+					x: x as u32,
+					y: y as u32,
+					width: width as u32,
+					height: height as u32
+				},
+				sprites
+			});
+		}
+	}
+	Atlas {
+		width: layout.band_width as u32,
+		height: layout.height(num_rows) as u32,
+		frames
+	}
+}
+
+// direct color images have no palettes, so they get a single row with an empty palette
+static NO_PALETTE: [Vec<Rgba<u8>>; 1] = [Vec::new()];
+
+// the palettes to draw an image with, one spritesheet row each
+pub fn palette_rows<'a>(def: &ImageDef, palettes: &'a [Vec<Rgba<u8>>]) -> &'a [Vec<Rgba<u8>>] {
+	if let PixelDataType::Direct = def.pixel_data_type { &NO_PALETTE } else { palettes }
+}
+
+// draws every sprite with a single palette. sprites decode independently, on the same thread pool as
+// the images themselves; collecting keeps them in order. anything worth a warning is only logged
+// once they're collected, on the calling thread
+pub fn make_sprites(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], palette: &[Rgba<u8>], options: &DecodeOptions) -> Result<Vec<RgbaImage>, DecodeError> {
+	match def.pixel_data_type {
+		PixelDataType::Bpp(bpp) => draw_sprites(def, &decode_sprite_indices(def, pixel_data_per_sprite, bpp, options)?, palette, options),
+		PixelDataType::Direct => {
+			let sprites: Vec<(RgbaImage, usize)> = pixel_data_per_sprite.par_iter().map(|pixel_data| {
+				let bytes = decompress_sprite(pixel_data, def)?;
+				Ok((make_direct_sprite(&bytes, def, options), bytes.len()))
+			}).collect::<Result<_, DecodeError>>()?;
+			Ok(sprites.into_iter().map(|(sprite, len)| {
+				warn_wrong_pixel_count(len, def);
+				sprite
+			}).collect())
+		}
+	}
+}
+
+// palette indices of every sprite, decompressed and unpacked once so they can be drawn with any palette
+pub fn decode_sprite_indices(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], bpp: usize, options: &DecodeOptions) -> Result<Vec<Vec<u8>>, DecodeError> {
+	let indices_per_sprite: Vec<Vec<u8>> = pixel_data_per_sprite.par_iter().map(|pixel_data| {
+		unpack_indices(&decompress_sprite(pixel_data, def)?, def, bpp, options)
+	}).collect::<Result<_, DecodeError>>()?;
+	for indices in &indices_per_sprite {
+		warn_wrong_index_count(indices, def);
+	}
+	Ok(indices_per_sprite)
+}
+
+// draws the indices from decode_sprite_indices with a single palette
+fn draw_sprites(def: &ImageDef, indices_per_sprite: &[Vec<u8>], palette: &[Rgba<u8>], options: &DecodeOptions) -> Result<Vec<RgbaImage>, DecodeError> {
+	let sprites: Vec<(RgbaImage, usize)> = indices_per_sprite.par_iter()
+		.map(|indices| make_indexed_sprite(indices, def, palette, options))
+		.collect::<Result<_, DecodeError>>()?;
+	Ok(sprites.into_iter().enumerate().map(|(k, (sprite, num_invalid))| {
+		if num_invalid > 0 {
+			warn!("sprite {}: {} pixels had out-of-range color indices", k, num_invalid);
+		}
+		sprite
+	}).collect())
+}
+
+// draws every subimage with a single palette, each one a grid of image_width x image_height sprites
+pub fn make_subimages(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], palette: &[Rgba<u8>], options: &DecodeOptions) -> Result<Vec<RgbaImage>, DecodeError> {
+	let sprites = make_sprites(def, pixel_data_per_sprite, palette, options)?;
+	assemble_subimages(def, &sprites, 0)
+}
+
+fn assemble_subimages<P: Pixel<Subpixel = u8>>(def: &ImageDef, sprites: &[ImageBuffer<P, Vec<u8>>], gutter: usize) -> Result<Vec<ImageBuffer<P, Vec<u8>>>, DecodeError> {
+	let sprites_per_subimage = def.image_width * def.image_height;
+	(0..def.num_subimages).map(|j| {
+		let a = j * sprites_per_subimage;
+		let b = (a + sprites_per_subimage).min(sprites.len());
+		make_subimage(&sprites[a..b], def, gutter)
+	}).collect()
+}
+
+pub fn make_spritesheet(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], palettes: &[Vec<Rgba<u8>>], options: &DecodeOptions) -> Result<RgbaImage, DecodeError> {
+	let palettes = palette_rows(def, palettes);
+
+	let layout = sheet_layout(def, options);
+	let spritesheet_width = layout.band_width;
+	let spritesheet_height = layout.height(palettes.len());
+	let mut img = RgbaImage::new(spritesheet_width as u32, spritesheet_height as u32);
+
+	// only the palette changes from row to row, so indexed sprites are decompressed just once
+	let indices_per_sprite = match def.pixel_data_type {
+		PixelDataType::Bpp(bpp) => Some(decode_sprite_indices(def, pixel_data_per_sprite, bpp, options)?),
+		PixelDataType::Direct => None
+	};
+	for (i, palette) in palettes.iter().enumerate() {
+		let sprites = match &indices_per_sprite {
+			Some(indices_per_sprite) => draw_sprites(def, indices_per_sprite, palette, options)?,
+			None => make_sprites(def, pixel_data_per_sprite, palette, options)?
+		};
+		let subimages = assemble_subimages(def, &sprites, layout.gutter)?;
+		for (j, subimage) in subimages.iter().enumerate() {
+			let (x, y) = layout.origin(i, j);
+			img.copy_from(subimage, x as u32, y as u32).map_err(DecodeError::SubimageCopy)?;
+		}
+	}
+	Ok(img)
+}
+
+// palette index that make_index_sheet fills gutters, missing sprites and missing pixels with: the
+// transparent index if there is one, otherwise the first index no pixel can have, so it can be given a
+// transparent color of its own. 8 bpp images without transparency use every index, so they have none
+pub fn index_sheet_fill(def: &ImageDef, options: &DecodeOptions) -> Option<u8> {
+	let PixelDataType::Bpp(bpp) = def.pixel_data_type else {
+		return None;
+	};
+	options.transparent_index(def).and_then(|index| u8::try_from(index).ok())
+		.or_else(|| u8::try_from(1usize << bpp).ok())
+}
+
+// lays out a single band of subimages like make_spritesheet, but as raw palette indices instead of
+// colors, so the image can be saved with its original palette. direct color images have no indices
+pub fn make_index_sheet(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], options: &DecodeOptions) -> Result<Option<GrayImage>, DecodeError> {
+	let PixelDataType::Bpp(bpp) = def.pixel_data_type else {
+		return Ok(None);
+	};
+
+	// alpha marks the pixels that were drawn, so everything else can be filled afterwards
+	let sprites: Vec<GrayAlphaImage> = decode_sprite_indices(def, pixel_data_per_sprite, bpp, options)?.into_iter().map(|indices| {
+		let mut sprite = GrayAlphaImage::new(def.sprite_width_px as u32, def.sprite_height_px as u32);
+		for (i, index) in indices.into_iter().enumerate() {
+			let (x, y) = options.tile_order.position(i, def.sprite_width_px);
+			if x < def.sprite_width_px && y < def.sprite_height_px {
+				sprite.put_pixel(x as u32, y as u32, LumaA([index, 255]));
+			}
+		}
+		sprite
+	}).collect();
+
+	let layout = sheet_layout(def, options);
+	let mut img = GrayAlphaImage::new(layout.band_width as u32, layout.band_height as u32);
+	for (j, subimage) in assemble_subimages(def, &sprites, layout.gutter)?.iter().enumerate() {
+		let (x, y) = layout.origin(0, j);
+		img.copy_from(subimage, x as u32, y as u32).map_err(DecodeError::SubimageCopy)?;
+	}
+	let fill = index_sheet_fill(def, options).unwrap_or(0);
+	Ok(Some(GrayImage::from_fn(img.width(), img.height(), |x, y| {
+		let LumaA([index, alpha]) = *img.get_pixel(x, y);
+		Luma([if alpha == 0 { fill } else { index }])
+	})))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// an uncompressed header with no transparency, followed by palette_bytes of palettes and
+	// pixel_bytes of pixel data
+	fn header(bpp_code: u8, num_sprites: u16, sprite_size: (u8, u8), grid: (u8, u8), num_palettes: u8, palette_bytes: usize, pixel_bytes: usize) -> Vec<u8> {
+		let pixel_data_offset = IMAGE_DEF_SIZE + palette_bytes;
+		let mut bytes = Vec::new();
+		bytes.extend(((pixel_data_offset + pixel_bytes) as u32).to_le_bytes());
+		bytes.extend([0, bpp_code]);
+		bytes.extend(num_sprites.to_le_bytes());
+		bytes.extend([sprite_size.0, sprite_size.1, 0, 0, grid.0, grid.1, DEFAULT_UNKNOWN, num_palettes]);
+		bytes.extend(0u16.to_le_bytes());
+		bytes.extend((IMAGE_DEF_SIZE as u16).to_le_bytes());
+		bytes.extend((pixel_data_offset as u16).to_le_bytes());
+		bytes.extend(0u16.to_le_bytes());
+		bytes
+	}
+
+	// a single-sprite, uncompressed image with one palette
+	fn test_def(pixel_data_type: PixelDataType, sprite_width_px: usize, sprite_height_px: usize) -> ImageDef {
+		ImageDef {
+			data_length: 0,
+			has_transparency: false,
+			is_encrypted: false,
+			compression: CompressionType::None,
+			pixel_data_type,
+			num_sprites: 1,
+			sprite_width_px,
+			sprite_height_px,
+			offset_x: 0,
+			offset_y: 0,
+			image_width: 1,
+			image_height: 1,
+			unknown: DEFAULT_UNKNOWN,
+			num_palettes: 1,
+			transparent_color_index: 0,
+			palette_data_offset: IMAGE_DEF_SIZE,
+			pixel_data_offset: IMAGE_DEF_SIZE,
+			padding: 0,
+			num_subimages: 1
+		}
+	}
+
+	#[test]
+	fn index_sheet_fills_empty_areas_with_a_transparent_index() {
+		let options = DecodeOptions { gutter: 1, ..DecodeOptions::default() };
+		let mut def = test_def(PixelDataType::Bpp(4), 2, 2);
+		let pixel_data = vec![vec![0x11, 0x11]];
+		let sheet = make_index_sheet(&def, &pixel_data, &options).unwrap().unwrap();
+		assert_eq!(sheet.pixels().filter(|pixel| pixel[0] == 1).count(), 4);
+		assert!(sheet.pixels().all(|pixel| pixel[0] == 1 || pixel[0] == 16));
+
+		def.has_transparency = true;
+		def.transparent_color_index = 3;
+		let sheet = make_index_sheet(&def, &[vec![0x11]], &options).unwrap().unwrap();
+		assert_eq!(sheet.pixels().filter(|pixel| pixel[0] == 1).count(), 2);
+		assert!(sheet.pixels().all(|pixel| pixel[0] == 1 || pixel[0] == 3));
+	}
+
+	#[test]
+	fn bits_round_trip_every_byte() {
+		for byte in 0..=255u8 {
+			assert_eq!(bits_to_byte(&byte_to_bits(byte)), byte);
+		}
+	}
+
+	#[test]
+	fn first_bit_is_least_significant() {
+		assert_eq!(byte_to_bits(0b0000_0001), [1, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(byte_to_bits(0b1000_0000), [0, 0, 0, 0, 0, 0, 0, 1]);
+		assert_eq!(bits_to_byte(&[1]), 1);
+		assert_eq!(bits_to_byte(&[0]), 0);
+		assert_eq!(bits_to_byte(&[1, 0]), 1);
+		assert_eq!(bits_to_byte(&[0, 1]), 2);
+		assert_eq!(bits_to_byte(&[1, 0, 0, 0]), 1);
+		assert_eq!(bits_to_byte(&[0, 0, 0, 1]), 8);
+		assert_eq!(bits_to_byte(&[1, 1, 0, 1]), 11);
+	}
+
+	#[test]
+	fn chunks_come_from_the_low_bits_first() {
+		let bits = byte_to_bits(0b1110_0100);
+		let chunks = |bpp| bits.chunks(bpp).map(bits_to_byte).collect::<Vec<u8>>();
+		assert_eq!(chunks(1), [0, 0, 1, 0, 0, 1, 1, 1]);
+		assert_eq!(chunks(2), [0, 1, 2, 3]);
+		assert_eq!(chunks(4), [4, 14]);
+	}
+
+	#[test]
+	fn padding_bits_in_the_last_byte_are_not_pixels() {
+		let options = DecodeOptions { strict: true, ..DecodeOptions::default() };
+		let def = test_def(PixelDataType::Bpp(1), 5, 3);
+		let indices = decode_indices(&[0b1010_1011, 0b0111_0101], &def, 1, &options).unwrap();
+		assert_eq!(indices, [1, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 1, 1]);
+
+		let def = test_def(PixelDataType::Bpp(4), 3, 1);
+		assert_eq!(decode_indices(&[0x21, 0x03], &def, 4, &options).unwrap(), [1, 2, 3]);
+	}
+
+	#[test]
+	fn strict_rejects_short_and_long_sprites() {
+		let options = DecodeOptions { strict: true, ..DecodeOptions::default() };
+		let def = test_def(PixelDataType::Bpp(1), 5, 3);
+		assert!(decode_indices(&[0xff], &def, 1, &options).is_err());
+		assert!(decode_indices(&[0xff, 0xff, 0xff], &def, 1, &options).is_err());
+	}
+
+	#[test]
+	fn direct_sprite_fills_exactly_its_pixels() {
+		let def = test_def(PixelDataType::Direct, 3, 2);
+		let values: Vec<u8> = (1..=8u16).flat_map(|value| (value << 11).to_le_bytes()).collect();
+		let sprite = make_direct_sprite(&values, &def, &DecodeOptions::default());
+		assert_eq!(sprite.dimensions(), (3, 2));
+		for (i, pixel) in sprite.pixels().enumerate() {
+			assert_eq!(*pixel, parse_rgb565((i as u16 + 1) << 11, ColorExpansion::default()));
+		}
+	}
+
+	#[test]
+	fn wordwise_words_are_little_endian() {
+		let stream = [
+			0x02, 0x00, 0x00, 0x80, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+			0x03, 0x00, 0x00, 0x00, 0xaa, 0xbb, 0xcc, 0xdd
+		];
+		assert_eq!(decompress_wordwise(&stream, 20).unwrap(), [
+			0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+			0xaa, 0xbb, 0xcc, 0xdd, 0xaa, 0xbb, 0xcc, 0xdd, 0xaa, 0xbb, 0xcc, 0xdd
+		]);
+	}
+
+	#[test]
+	fn wordwise_rejects_truncated_runs() {
+		let literal = [0x03, 0x00, 0x00, 0x80, 0x01, 0x02, 0x03, 0x04];
+		assert!(matches!(decompress_wordwise(&literal, 12), Err(DecompressError::Truncated { offset: 0, needed: 12, available: 4 })));
+		let repeat = [0x01, 0x00, 0x00, 0x00];
+		assert!(matches!(decompress_wordwise(&repeat, 4), Err(DecompressError::Truncated { offset: 0, needed: 4, available: 0 })));
+	}
+
+	#[test]
+	fn wordwise_rejects_runs_longer_than_the_sprite() {
+		let stream = [0xff, 0xff, 0xff, 0x0f, 0x01, 0x02, 0x03, 0x04];
+		assert!(matches!(decompress_wordwise(&stream, 8), Err(DecompressError::RunTooLong { .. })));
+	}
+
+	#[test]
+	fn leftover_sprites_get_a_padded_subimage() {
+		let def = parse_image_def(&header(2, 5, (2, 2), (2, 1), 1, 32, 10), Endian::Little).unwrap();
+		assert_eq!(def.num_subimages, 3);
+
+		let red = Rgba([255, 0, 0, 255]);
+		let spritesheet = make_spritesheet(&def, &vec![vec![0x11, 0x11]; 5], &[vec![Rgba([0, 0, 0, 255]), red]], &DecodeOptions::default()).unwrap();
+		assert_eq!(spritesheet.dimensions(), (12, 2));
+		assert_eq!(*spritesheet.get_pixel(9, 1), red);
+		assert_eq!(spritesheet.get_pixel(10, 0)[3], 0);
+		assert_eq!(spritesheet.get_pixel(11, 1)[3], 0);
+	}
+
+	#[test]
+	fn direct_transparency_matches_the_color_value() {
+		let mut def = test_def(PixelDataType::Direct, 3, 1);
+		def.has_transparency = true;
+		def.transparent_color_index = 0xf81f;
+		let values: Vec<u8> = [0xf81f, 0x001f, 0xf81e].iter().flat_map(|value: &u16| value.to_le_bytes()).collect();
+		let sprite = make_direct_sprite(&values, &def, &DecodeOptions::default());
+		assert_eq!(*sprite.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+		assert_eq!(*sprite.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+		assert_eq!(sprite.get_pixel(2, 0)[3], 255);
+	}
+
+	#[test]
+	fn odd_length_direct_data_stops_at_the_last_whole_pixel() {
+		let def = test_def(PixelDataType::Direct, 2, 2);
+		let sprite = make_direct_sprite(&[0xff, 0xff, 0x1f, 0x00, 0xff], &def, &DecodeOptions::default());
+		assert_eq!(*sprite.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+		assert_eq!(*sprite.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+		assert_eq!(*sprite.get_pixel(0, 1), Rgba([0, 0, 0, 0]));
+		assert_eq!(*sprite.get_pixel(1, 1), Rgba([0, 0, 0, 0]));
+	}
+
+	#[test]
+	fn hand_built_wordwise_stream_round_trips() {
+		let stream = [
+			0x02, 0x00, 0x00, 0x00, 0x10, 0x20, 0x30, 0x40,
+			0x01, 0x00, 0x00, 0x80, 0x0a, 0x0b, 0x0c, 0x0d
+		];
+		let data = decompress_wordwise(&stream, 12).unwrap();
+		assert_eq!(data, [0x10, 0x20, 0x30, 0x40, 0x10, 0x20, 0x30, 0x40, 0x0a, 0x0b, 0x0c, 0x0d]);
+		assert_eq!(compress_wordwise(&data).unwrap(), stream);
+	}
+
+	#[test]
+	fn bit_replication_expands_known_values() {
+		let expand = |value, bits| ColorExpansion::BitReplication.expand(value, bits);
+		assert_eq!([expand(0, 5), expand(1, 5), expand(16, 5), expand(31, 5)], [0, 8, 132, 255]);
+		assert_eq!([expand(0, 6), expand(1, 6), expand(32, 6), expand(63, 6)], [0, 4, 130, 255]);
+		assert_eq!(ColorExpansion::Linear.expand(16, 5), 131);
+		assert_eq!(parse_rgb565((16 << 11) | (32 << 5) | 1, ColorExpansion::BitReplication), Rgba([132, 130, 8, 255]));
+	}
+
+	#[test]
+	fn row_aligned_sprites_skip_each_rows_padding() {
+		let options = DecodeOptions { row_aligned: true, strict: true, ..DecodeOptions::default() };
+		let def = test_def(PixelDataType::Bpp(1), 5, 3);
+		let indices = decode_indices(&[0b111_10011, 0b000_01110, 0b101_11111], &def, 1, &options).unwrap();
+		assert_eq!(indices, [1, 1, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1]);
+	}
+
+	// xorshift, so property tests are random but repeatable without pulling in a crate. values come
+	// in runs of random length, so both kinds of compressed run get exercised
+	fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+		let mut state = seed.max(1);
+		let mut next = move || {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state
+		};
+		let mut bytes = Vec::new();
+		while bytes.len() < len {
+			let value = next() as u8;
+			let run = (next() % 200) as usize * (next() % 2) as usize + 1;
+			bytes.extend(std::iter::repeat_n(value, run.min(len - bytes.len())));
+		}
+		bytes
+	}
+
+	#[test]
+	fn bytewise_round_trips_random_data() {
+		for seed in 1..200 {
+			let data = random_bytes(seed, (seed as usize * 37) % 1000);
+			assert_eq!(decompress_bytewise(&compress_bytewise(&data)).unwrap(), data);
+		}
+	}
+
+	#[test]
+	fn wordwise_round_trips_a_known_pattern() {
+		let words: [u32; 9] = [0x04030201, 0x04030201, 0x04030201, 0xdeadbeef, 0x00000000, 0x11223344, 0x11223344, 0x80000001, 0x0fffffff];
+		let data: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+		let compressed = compress_wordwise(&data).unwrap();
+		assert_eq!(compressed[..8], [0x03, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04]);
+		assert_eq!(decompress_wordwise(&compressed, data.len()).unwrap(), data);
+	}
+
+	#[test]
+	fn wordwise_round_trips_and_rejects_partial_words() {
+		for seed in 1..200 {
+			let data = random_bytes(seed, (seed as usize * 4) % 800);
+			assert_eq!(decompress_wordwise(&compress_wordwise(&data).unwrap(), data.len()).unwrap(), data);
+		}
+		assert!(matches!(compress_wordwise(&[1, 2, 3, 4, 5]), Err(EncodeError::UnalignedWords(5))));
+	}
+
+	#[test]
+	fn bytewise_counts_never_pass_127() {
+		let literal: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+		for data in [vec![7; 1000], literal, random_bytes(42, 5000)] {
+			let compressed = compress_bytewise(&data);
+			let mut i = 0;
+			while i < compressed.len() {
+				let n = (compressed[i] & 0x7f) as usize;
+				assert!((1..=0x7f).contains(&n));
+				i += 1 + if compressed[i] & 0x80 != 0 { n } else { 1 };
+			}
+			assert_eq!(decompress_bytewise(&compressed).unwrap(), data);
+		}
+	}
+
+	#[test]
+	fn indexed_images_without_palettes_draw_in_grayscale() {
+		let mut data = header(2, 1, (2, 1), (1, 1), 0, 0, 1);
+		data.push(0xf0);
+		let image = decode_image(&data, 0, &DecodeOptions::default()).unwrap();
+		assert_eq!(image.palettes.len(), 1);
+		assert_eq!(image.palettes[0].len(), 16);
+		let sheet = make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &DecodeOptions::default()).unwrap();
+		assert_eq!(sheet.dimensions(), (2, 1));
+		assert_eq!(*sheet.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+		assert_eq!(*sheet.get_pixel(1, 0), Rgba([255, 255, 255, 255]));
+	}
+
+	#[test]
+	fn zero_subimage_dimensions_are_rejected() {
+		let result = parse_image_def(&header(3, 4, (2, 2), (0, 2), 1, 512, 16), Endian::Little);
+		assert!(matches!(result, Err(DecodeError::Parse(ParseError::BadDimensions { width: 0, height: 2 }))));
+
+		let mut def = test_def(PixelDataType::Bpp(8), 2, 2);
+		def.image_width = 0;
+		assert!(matches!(make_subimage::<Rgba<u8>>(&[], &def, 0), Err(DecodeError::Parse(ParseError::BadDimensions { .. }))));
+	}
+
+	#[test]
+	fn zero_sprite_dimensions_are_rejected() {
+		let result = parse_image_def(&header(2, 1, (0, 4), (1, 1), 1, 32, 8), Endian::Little);
+		assert!(matches!(result, Err(DecodeError::Parse(ParseError::BadDimensions { width: 0, height: 4 }))));
+
+		let mut data = header(2, 1, (4, 4), (1, 1), 1, 32, 8);
+		data.resize(IMAGE_DEF_SIZE + 40, 0);
+		for options in [DecodeOptions { sprite_width: Some(0), ..DecodeOptions::default() }, DecodeOptions { sprite_height: Some(0), ..DecodeOptions::default() }] {
+			assert!(matches!(decode_image(&data, 0, &options), Err(DecodeError::Parse(ParseError::BadDimensions { .. }))));
+		}
+	}
+
+	#[test]
+	fn tiled_sprites_deinterleave_by_8x8_tile() {
+		let options = DecodeOptions { tile_order: TileOrder::Tiled8x8, ..DecodeOptions::default() };
+		let def = test_def(PixelDataType::Bpp(8), 16, 16);
+		// each stored byte is its own position in the stream, drawn as that shade of gray
+		let stored: Vec<u8> = (0..=255).collect();
+		let sprite = make_sprites(&def, &[stored], &grayscale_palette(256), &options).unwrap().remove(0);
+
+		assert_eq!(sprite.get_pixel(7, 0)[0], 7);
+		assert_eq!(sprite.get_pixel(0, 1)[0], 8);
+		assert_eq!(sprite.get_pixel(8, 0)[0], 64);
+		assert_eq!(sprite.get_pixel(0, 8)[0], 128);
+		assert_eq!(sprite.get_pixel(15, 15)[0], 255);
+		for (x, y, pixel) in sprite.enumerate_pixels() {
+			let tile = (y / 8) * 2 + x / 8;
+			assert_eq!(pixel[0] as u32, tile * 64 + (y % 8) * 8 + x % 8);
+		}
+	}
+}
@@ -1,419 +1,1462 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
-use std::env::args;
+use std::ffi::OsStr;
 use std::fs;
-use bytes::{ Bytes, Buf };
-use image::{ Rgba, RgbaImage, GenericImage };
-
-// Format: https://gist.github.com/GMMan/a467961057d1e9fb08a2bbfd553180d6
-
-#[derive(Debug)]
-enum CompressionType {
-	None,
-	Bytewise,
-	Wordwise
-}
-
-#[derive(Debug)]
-enum PixelDataType {
-	Bpp(usize),
-	Direct
-}
-
-struct ImageDef {
-	data_length: usize,
-	has_transparency: bool,
-	is_encrypted: bool,
-	compression: CompressionType,
-	pixel_data_type: PixelDataType,
-	num_sprites: usize,
-	sprite_width_px: usize,
-	sprite_height_px: usize,
-	offset_x: i8,
-	offset_y: i8,
-	image_width: usize,
-	image_height: usize,
-	num_palettes: usize,
-	transparent_color_index: u16,
-	palette_data_offset: usize,
-	pixel_data_offset: usize,
-	num_subimages: usize
+use std::path::{ Path, PathBuf };
+use std::io::{ self, Cursor, IsTerminal, Read, Write };
+use std::process::exit;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use clap::{ Parser, Subcommand, ValueEnum };
+use image::{ Delay, Frame, GrayImage, ImageBuffer, ImageFormat, Pixel, Rgba, RgbaImage, imageops };
+use image::imageops::FilterType;
+use image::codecs::gif::{ GifEncoder, Repeat };
+use indicatif::{ ProgressBar, ProgressDrawTarget, ProgressStyle };
+use log::{ Level, error, info, log, warn };
+use rayon::prelude::*;
+use serde::{ Serialize, Deserialize };
+use paradoodle::{ ColorExpansion, ColorFormat, CompressionType, Endian, TileOrder, PixelDataType, DecodeError, DecodeOptions, ImageDef, read_offset_table, read_image_def, decode_image, palette_rows, make_sprites, make_subimages, make_spritesheet, make_atlas, make_index_sheet, index_sheet_fill, sprite_placements, trim_bounds, Rect, SpritePlacement };
+use paradoodle::contact_sheet::make_contact_sheet;
+use paradoodle::html::make_preview_page;
+use paradoodle::encode::{ EncodeOptions, ImageMetadata, encode, sprites_to_image };
+use paradoodle::palette::{ to_gpl, to_jasc, to_act };
+
+// a type alias, so clap parses the whole list from a single value
+type Indices = Vec<usize>;
+
+/// Extracts PNG spritesheets from packed sprite image files
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+	#[command(subcommand)]
+	command: Option<Command>,
+
+	#[command(flatten)]
+	args: Args
 }
 
-fn main() -> Result<(), Box<dyn Error + 'static>> {
-	let input_path = args().nth(1).expect("no input path given");
-	let mut output_path = args().nth(2).expect("no output path given");
-	if !output_path.ends_with('/') {
-		output_path = format!("{}/", output_path);
+#[derive(Subcommand)]
+enum Command {
+	/// Rebuild a file from spritesheets and their metadata sidecars
+	Encode {
+		/// File to write
+		output: String,
+		/// Pairs of spritesheet and metadata paths, e.g. image-0.png image-0.json
+		#[arg(required = true)]
+		inputs: Vec<String>,
+		#[command(flatten)]
+		encryption: Encryption,
+		/// Dither direct color images instead of rounding each pixel to the nearest color
+		#[arg(long)]
+		dither: bool,
+		/// Rebuild the palette of indexed images from their spritesheet's colors, keeping only the
+		/// first palette, for spritesheets edited with colors that aren't in the original palette
+		#[arg(long)]
+		quantize: bool
+	},
+	/// Rebuild a file as 8 bpp, uncompressed images from individual sprites
+	EncodeSprites {
+		/// File to write
+		output: String,
+		/// Directory of sprites from --split-sprites, named image-N-palette-0-sprite-S.png
+		sprites: String,
+		/// Table of contents from --json
+		metadata: String,
+		#[command(flatten)]
+		encryption: Encryption
 	}
+}
+
+#[derive(clap::Args)]
+struct Encryption {
+	/// Encrypt every image, whatever its metadata says
+	#[arg(long)]
+	encrypt: bool,
+
+	/// XOR key for encrypted pixel data
+	#[arg(long, value_parser = parse_byte, default_value = "0x53")]
+	key: u8
+}
+
+#[derive(clap::Args)]
+struct Args {
+	/// File to extract images from, a directory of files, or - for stdin
+	#[arg(value_name = "INPUT", conflicts_with = "input")]
+	input_path: Option<String>,
+
+	/// Directory to write images to, or - to write a single image to stdout
+	#[arg(value_name = "OUTPUT", conflicts_with = "output")]
+	output_path: Option<String>,
+
+	/// File to extract images from, a directory of files, or - for stdin
+	#[arg(short, long)]
+	input: Option<String>,
+
+	/// Directory to write images to, or - to write a single image to stdout
+	#[arg(short, long)]
+	output: Option<String>,
+
+	/// Write a single image to stdout, same as an output of -
+	#[arg(long, conflicts_with_all = ["output", "output_path"])]
+	stdout: bool,
+
+	/// Only extract these images, e.g. "3,5,12" or "10-15"
+	#[arg(long, value_parser = parse_index_list)]
+	only: Option<Indices>,
+
+	/// Only extract the image at this index
+	#[arg(long, value_name = "N", conflicts_with = "only")]
+	image: Option<usize>,
+
+	/// Print each image's header values without extracting anything
+	#[arg(long)]
+	list: bool,
+
+	/// Print where each image's header, palettes and pixel data sit in the file, and any gaps
+	/// between images, without extracting anything
+	#[arg(long, conflicts_with_all = ["list", "json"])]
+	layout: bool,
+
+	/// Check every image header for consistency and report PASS/FAIL, without extracting anything
+	#[arg(long, conflicts_with = "list")]
+	check: bool,
+
+	/// Print each image's header values to stdout as a JSON array, without extracting anything
+	#[arg(long, conflicts_with = "list")]
+	json: bool,
+
+	/// Write each palette to image-N-palette-M.pal as raw little-endian colors in --color-format, and
+	/// the transparent palette index to image-N-palette.txt
+	#[arg(long)]
+	dump_palettes: bool,
+
+	/// Write each sprite to its own file for every palette, instead of a spritesheet
+	#[arg(long)]
+	split_sprites: bool,
+
+	/// Crop each --split-sprites file to its pixels that aren't fully transparent, recording the crop
+	/// in the manifest
+	#[arg(long, requires = "split_sprites")]
+	trim: bool,
+
+	/// What --trim does with sprites that are fully transparent
+	#[arg(long, value_enum, value_name = "MODE", default_value = "skip", requires = "trim")]
+	trim_empty: TrimEmpty,
+
+	/// Write each palette to image-N-palette-M in this format, for use in image editors
+	#[arg(long, value_enum, value_name = "FORMAT")]
+	export_palettes: Option<PaletteFormat>,
+
+	/// Write each subimage to its own file for every palette, instead of a spritesheet
+	#[arg(long, conflicts_with = "split_sprites")]
+	split_subimages: bool,
+
+	/// Write image-N.gif with each subimage as a frame, instead of a spritesheet
+	#[arg(long, conflicts_with_all = ["split_sprites", "split_subimages"])]
+	gif: bool,
+
+	/// Write image-N.png as an animated PNG with each subimage as a frame, instead of a spritesheet
+	#[arg(long, conflicts_with_all = ["split_sprites", "split_subimages", "gif"])]
+	apng: bool,
+
+	/// Palette to draw --gif and --apng frames with [default: --palette, or 0]
+	#[arg(long, value_name = "P", alias = "gif-palette")]
+	frame_palette: Option<usize>,
+
+	/// Frames per second for --gif and --apng
+	#[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..))]
+	fps: u16,
+
+	/// Number of times --apng animations play, or 0 to loop forever
+	#[arg(long, default_value_t = 0)]
+	loops: u32,
+
+	/// Write image-N-atlas.json describing where each subimage and sprite sits in the spritesheet
+	#[arg(long)]
+	atlas: bool,
+
+	/// Write indexed images as paletted PNGs, one image-N-palette-M.png per palette; direct color
+	/// images are still written as RGBA
+	#[arg(long, conflicts_with_all = ["split_sprites", "split_subimages", "gif", "apng", "format"])]
+	indexed_png: bool,
+
+	/// Names for output files, with {index}, {stem}, {palette}, {frame} (or {subimage}) and {sprite}
+	/// placeholders. Numbers take a width, e.g. {index:03}, and ones the template leaves out are
+	/// appended, e.g. -palette-1, or just -3 for the index. A trailing file extension is optional
+	#[arg(long, visible_alias = "output-name", value_name = "TEMPLATE", default_value = "image-{index}", value_parser = parse_name_template)]
+	name_template: String,
+
+	/// Leave output files that already exist untouched, without decoding images whose files all exist
+	#[arg(long, overrides_with = "overwrite")]
+	skip_existing: bool,
+
+	/// Rewrite output files that already exist, the default
+	#[arg(long, overrides_with = "skip_existing")]
+	overwrite: bool,
+
+	/// Also write index.png with a labeled thumbnail of every extracted image's first palette row
+	#[arg(long)]
+	contact_sheet: bool,
+
+	/// Also write index.html showing every spritesheet with its header values
+	#[arg(long, conflicts_with_all = ["split_sprites", "split_subimages", "indexed_png"])]
+	html: bool,
+
+	/// Number of thumbnails per row in --contact-sheet [default: enough for a square grid]
+	#[arg(long, value_name = "N", requires = "contact_sheet")]
+	contact_sheet_columns: Option<usize>,
+
+	/// Draw images over this solid color instead of leaving them transparent; --indexed-png images
+	/// keep their transparency
+	#[arg(long, value_name = "RRGGBB", value_parser = parse_rgb)]
+	background: Option<[u8; 3]>,
+
+	/// Scale every image up by this whole number before saving, keeping pixels sharp; --atlas and
+	/// --split-sprites coordinates are scaled to match
+	#[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+	scale: u32,
+
+	/// Image format to write spritesheets as
+	#[arg(long, value_enum, default_value = "png")]
+	format: Format,
 
-	let data = fs::read(input_path)?;
-	let mut buffer = Bytes::copy_from_slice(&data);
+	/// Only draw this palette, so spritesheets are a single row
+	#[arg(long, value_name = "N")]
+	palette: Option<usize>,
 
-	// get image offsets
-	let first_image_offset = buffer.get_u32_le();
-	let mut image_offsets: Vec<u32> = vec![first_image_offset];
-	let mut current_offset = 4;
-	while current_offset < first_image_offset {
-		let image_offset = buffer.get_u32_le();
-		image_offsets.push(image_offset);
-		current_offset += 4;
+	/// Write a JSON sidecar with each image's header values and palettes, for rebuilding the file with
+	/// encode, which expects a single spritesheet per image in the default layout and colors
+	#[arg(long, conflicts_with_all = ["palette", "scale", "gutter", "columns", "apply_offsets", "background", "transparent_index", "transparent_color", "highlight_invalid", "split_sprites", "split_subimages", "gif", "apng", "indexed_png"])]
+	metadata: bool,
+
+	/// Place each subimage at the header's offset_x/offset_y, padding every cell in every palette
+	/// row by the size of the offset
+	#[arg(long)]
+	apply_offsets: bool,
+
+	/// Bit layout of palette colors and direct color pixels
+	#[arg(long, value_enum, default_value = "rgb565")]
+	color_format: ColorFormatArg,
+
+	/// How 5- and 6-bit color channels are widened to 8 bits
+	#[arg(long, value_enum, default_value = "bit-replication")]
+	color_expansion: ColorExpansionArg,
+
+	/// Byte order of the offset table, header fields, colors and compressed sprite offsets
+	#[arg(long, value_enum, default_value = "little")]
+	endian: EndianArg,
+
+	/// Order of pixels within an indexed sprite
+	#[arg(long, value_enum, default_value = "linear")]
+	tile_order: TileOrderArg,
+
+	/// Treat each row of an indexed sprite as padded to a whole number of bytes
+	#[arg(long)]
+	row_aligned: bool,
+
+	/// Number of threads to decode images and sprites with, or 0 to use every core
+	#[arg(long, short, default_value_t = 0)]
+	jobs: usize,
+
+	/// Don't show a progress bar while extracting, or a summary afterwards
+	#[arg(long, short)]
+	quiet: bool,
+
+	/// Draw this palette index transparent in indexed images, whatever the header says
+	#[arg(long, value_name = "N")]
+	transparent_index: Option<usize>,
+
+	/// Draw this color transparent in direct color images, whatever the header says
+	#[arg(long, value_name = "RRGGBB", value_parser = parse_rgb)]
+	transparent_color: Option<[u8; 3]>,
+
+	/// XOR key for encrypted pixel data
+	#[arg(long, value_parser = parse_byte, default_value = "0x53")]
+	key: u8,
+
+	/// Wrap subimages into a grid this many columns wide, instead of one row per palette
+	#[arg(long, value_name = "N")]
+	columns: Option<usize>,
+
+	/// Leave this many transparent pixels between sprites, subimages and palette rows in spritesheets
+	#[arg(long, value_name = "PX", default_value_t = 0)]
+	gutter: usize,
+
+	/// Decode sprites at this width, whatever the header says
+	#[arg(long, value_name = "PX")]
+	sprite_width: Option<usize>,
+
+	/// Decode sprites at this height, whatever the header says
+	#[arg(long, value_name = "PX")]
+	sprite_height: Option<usize>,
+
+	/// Fail on sprites whose pixel data is the wrong size or uses out-of-range color indices, instead of warning
+	#[arg(long)]
+	strict: bool,
+
+	/// Draw out-of-range color indices as magenta instead of transparent
+	#[arg(long)]
+	highlight_invalid: bool
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+	Png,
+	Bmp,
+	Tga,
+	Webp
+}
+
+impl Format {
+	// every one of these is written as 8-bit RGBA, so transparency survives in all of them
+	fn image_format(self) -> ImageFormat {
+		match self {
+			Format::Png => ImageFormat::Png,
+			Format::Bmp => ImageFormat::Bmp,
+			Format::Tga => ImageFormat::Tga,
+			Format::Webp => ImageFormat::WebP
+		}
 	}
+}
 
-	for (i, image_offset) in image_offsets.iter().enumerate() {
-		let image_buffer = Bytes::copy_from_slice(&data[*image_offset as usize..]);
-		let image_def = read_image_def(image_buffer);
+#[derive(Clone, Copy, ValueEnum)]
+enum PaletteFormat {
+	/// GIMP palette
+	Gpl,
+	/// Paint Shop Pro palette
+	Jasc,
+	/// Adobe color table
+	Act
+}
+
+impl PaletteFormat {
+	fn extension(self) -> &'static str {
+		match self {
+			PaletteFormat::Gpl => "gpl",
+			PaletteFormat::Jasc => "pal",
+			PaletteFormat::Act => "act"
+		}
+	}
+}
 
-		// calc data offsets
-		let start_index = *image_offset as usize;
-		let palette_data_index = start_index + image_def.palette_data_offset;
-		let pixel_data_index = start_index + image_def.pixel_data_offset;
-		let end_index = start_index + image_def.data_length;
+#[derive(Clone, Copy, ValueEnum)]
+enum TrimEmpty {
+	/// Write no file, and record a 0x0 crop
+	Skip,
+	/// Write the sprite uncropped
+	Keep
+}
 
-		println!("\nImage {}", i);
-		println!("    is_encrypted: {:?}", image_def.is_encrypted);
-		println!("    compression: {:?}", image_def.compression);
-		println!("    num_palettes: {}", image_def.num_palettes);
-		println!("    num_sprites: {}", image_def.num_sprites);
-		println!("    sprite_width_px: {}", image_def.sprite_width_px);
-		println!("    sprite_height_px: {}", image_def.sprite_height_px);
-		println!("    image_width: {}", image_def.image_width);
-		println!("    image_height: {}", image_def.image_height);
+#[derive(Clone, Copy, ValueEnum)]
+enum TileOrderArg {
+	Linear,
+	#[value(name = "tiled8x8")]
+	Tiled8x8
+}
 
-		// get color palettes
-		let mut palettes = Vec::new();
-		if let PixelDataType::Bpp(bpp) = image_def.pixel_data_type {
-			let palette_data = &data[palette_data_index..pixel_data_index];
-			let colors_per_palette = 2usize.pow(bpp as u32);
-			palettes = get_palettes(palette_data, colors_per_palette, image_def.num_palettes);
+impl TileOrderArg {
+	fn tile_order(self) -> TileOrder {
+		match self {
+			TileOrderArg::Linear => TileOrder::Linear,
+			TileOrderArg::Tiled8x8 => TileOrder::Tiled8x8
 		}
+	}
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EndianArg {
+	Little,
+	Big
+}
 
-		// get pixel data for each sprite
-		let pixel_data_per_sprite = get_pixel_data_per_sprite(&data[pixel_data_index..end_index], &image_def);
+impl EndianArg {
+	fn endian(self) -> Endian {
+		match self {
+			EndianArg::Little => Endian::Little,
+			EndianArg::Big => Endian::Big
+		}
+	}
+}
 
-		// combine sprites into subimages, and subimages into a spritesheet, one row per palette
-		let spritesheet = make_spritesheet(&image_def, &pixel_data_per_sprite, &palettes);
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorFormatArg {
+	Rgb565,
+	Rgb555,
+	Argb1555
+}
 
-		// save spritesheet
-		spritesheet.save(format!("{}image-{}.png", output_path, i)).expect("failed to save");
+impl ColorFormatArg {
+	fn color_format(self) -> ColorFormat {
+		match self {
+			ColorFormatArg::Rgb565 => ColorFormat::Rgb565,
+			ColorFormatArg::Rgb555 => ColorFormat::Rgb555,
+			ColorFormatArg::Argb1555 => ColorFormat::Argb1555
+		}
 	}
+}
 
-	Ok(())
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorExpansionArg {
+	/// Copy the high bits of each channel into the low bits
+	BitReplication,
+	/// Scale each channel by 255 / max and truncate, as older versions did. Mid-range colors come out
+	/// up to one 8-bit level darker than with bit replication
+	Linear
+}
+
+impl ColorExpansionArg {
+	fn color_expansion(self) -> ColorExpansion {
+		match self {
+			ColorExpansionArg::BitReplication => ColorExpansion::BitReplication,
+			ColorExpansionArg::Linear => ColorExpansion::Linear
+		}
+	}
+}
+
+// an entry in the --json table of contents
+#[derive(Serialize, Deserialize)]
+struct ImageRecord {
+	index: usize,
+	offset: u32,
+	#[serde(flatten)]
+	def: ImageDef
+}
+
+thread_local! {
+	// lines logged on this thread since the image it's extracting started, held back to print with the
+	// rest of that image's output, see extract_file
+	static IMAGE_LINES: RefCell<Option<Vec<(Level, String)>>> = const { RefCell::new(None) };
+}
+
+// counts warnings as they're printed, and keeps log lines from being drawn over the progress bar
+struct ProgressLogger {
+	inner: env_logger::Logger,
+	progress: ProgressBar,
+	num_warnings: AtomicUsize
+}
+
+impl log::Log for ProgressLogger {
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		self.inner.enabled(metadata)
+	}
+
+	fn log(&self, record: &log::Record) {
+		if !self.inner.matches(record) {
+			return;
+		}
+		let held = IMAGE_LINES.with_borrow_mut(|lines| lines.as_mut().map(|lines| lines.push((record.level(), record.args().to_string()))));
+		if held.is_some() {
+			return;
+		}
+		if record.level() <= Level::Warn {
+			self.num_warnings.fetch_add(1, Ordering::Relaxed);
+		}
+		self.progress.suspend(|| self.inner.log(record));
+	}
+
+	fn flush(&self) {
+		self.inner.flush();
+	}
+}
+
+// an entry in the --split-sprites manifest, with one file per palette
+#[derive(Serialize)]
+struct ManifestSprite {
+	#[serde(flatten)]
+	placement: SpritePlacement,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	trim: Option<Rect>,
+	files: Vec<String>
+}
+
+// where a file's images are written, named from --name-template with the image index zero-padded so
+// files sort in order, unless the template gives its own width. placeholders without a value are left empty
+struct Output<'a> {
+	dir: Option<&'a Path>,
+	template: &'a str,
+	stem: &'a str,
+	index_width: usize,
+	skip_existing: bool
+}
+
+impl Output<'_> {
+	fn to_stdout(&self) -> bool {
+		self.dir.is_none()
+	}
+
+	// numbers the template leaves out are appended, so every file still gets a unique name
+	fn name(&self, index: usize, palette: Option<usize>, frame: Option<usize>, sprite: Option<usize>) -> String {
+		let mut name = String::new();
+		let mut used = Vec::new();
+		let mut rest = self.template;
+		// parse_name_template already checked every placeholder is closed and known
+		while let Some(start) = rest.find('{') && let Some(end) = rest[start..].find('}').map(|end| start + end) {
+			name.push_str(&rest[..start]);
+			if let Some((key, width)) = parse_placeholder(&rest[(start + 1)..end]) {
+				let value = match key {
+					"index" => Some(index),
+					"palette" => palette,
+					"frame" => frame,
+					"sprite" => sprite,
+					_ => None
+				};
+				if key == "stem" {
+					name.push_str(self.stem);
+				} else if let Some(value) = value {
+					let width = width.unwrap_or(if key == "index" { self.index_width } else { 0 });
+					name.push_str(&format!("{:0width$}", value, width = width));
+				}
+				used.push(key);
+			}
+			rest = &rest[(end + 1)..];
+		}
+		name.push_str(rest);
+		// the image index is appended bare, since images are what a template without it would collide on
+		if !used.contains(&"index") {
+			name = format!("{}-{:0width$}", name, index, width = self.index_width);
+		}
+		for (label, value) in [("palette", palette), ("frame", frame), ("sprite", sprite)] {
+			if let Some(value) = value && !used.contains(&label) {
+				name = format!("{}-{}-{}", name, label, value);
+			}
+		}
+		name
+	}
+
+	fn path(&self, name: &str, suffix: &str) -> PathBuf {
+		self.dir.unwrap_or(Path::new("")).join(format!("{}{}", name, suffix))
+	}
+
+	// whether to leave a file that's already there alone, under --skip-existing
+	fn skip(&self, path: &Path, log: &mut Log) -> bool {
+		let skip = self.skip_existing && path.exists();
+		if skip {
+			log.info(format!("Skipping {}, it already exists", path.display()));
+		}
+		skip
+	}
+
+	// every file an image will be written to, when that's known without decoding it. split files and
+	// palette files depend on how many sprites and palettes the image has
+	fn known_paths(&self, i: usize, args: &Args) -> Option<Vec<PathBuf>> {
+		if args.split_sprites || args.split_subimages || args.indexed_png || args.dump_palettes || args.export_palettes.is_some() {
+			return None;
+		}
+		let name = self.name(i, None, None, None);
+		if args.apng {
+			return Some(vec![self.path(&name, ".png")]);
+		}
+		if args.gif {
+			return Some(vec![self.path(&name, ".gif")]);
+		}
+		let mut paths = vec![self.path(&name, &format!(".{}", args.format.image_format().extensions_str()[0]))];
+		if args.atlas {
+			paths.push(self.path(&name, "-atlas.json"));
+		}
+		if args.metadata {
+			paths.push(self.path(&name, ".json"));
+		}
+		Some(paths)
+	}
 }
 
-fn read_image_def(mut bytes: Bytes) -> ImageDef {
-	let data_length = bytes.get_u32_le() as usize;
+// what happened to a single image, for the summary at the end of a run. images skipped by
+// --skip-existing are never decoded, so they have no header
+#[derive(Default)]
+struct ImageSummary {
+	index: usize,
+	def: Option<ImageDef>,
+	bytes_written: u64,
+	num_warnings: usize,
+	failed: bool
+}
+
+// output for a single image, buffered so parallel images still log in order
+#[derive(Default)]
+struct Log {
+	lines: Vec<(Level, String)>,
+	summary: ImageSummary
+}
+
+impl Log {
+	fn wrote(&mut self, path: &Path) {
+		self.summary.bytes_written += fs::metadata(path).map_or(0, |metadata| metadata.len());
+	}
+
+	fn info(&mut self, line: String) {
+		self.push(Level::Info, line);
+	}
+
+	fn debug(&mut self, line: String) {
+		self.push(Level::Debug, line);
+	}
+
+	fn error(&mut self, line: String) {
+		self.push(Level::Error, line);
+	}
+
+	// anything the library logged since the last line goes first, so lines stay in the order they happened
+	fn push(&mut self, level: Level, line: String) {
+		self.take_held_lines();
+		self.lines.push((level, line));
+	}
 
-	// read flags
-	let flags = bytes.get_u8();
-	let has_transparency = (flags & 0b00000100) > 0;
-	let compression = if (flags & 0b00100000) > 0 {
-		CompressionType::Bytewise
-	} else if (flags & 0b01000000) > 0 {
-		CompressionType::Wordwise
+	fn take_held_lines(&mut self) {
+		let held = IMAGE_LINES.with_borrow_mut(|lines| lines.as_mut().map(std::mem::take)).unwrap_or_default();
+		self.summary.num_warnings += held.iter().filter(|(level, _)| *level <= Level::Warn).count();
+		self.lines.extend(held);
+	}
+
+	fn flush(&self) {
+		for (level, line) in &self.lines {
+			log!(*level, "{}", line);
+		}
+	}
+}
+
+fn main() -> Result<(), Box<dyn Error + 'static>> {
+	// warnings and progress go to stderr, set RUST_LOG=debug to also see each image's header. the
+	// progress bar stays hidden until there are images to extract
+	let inner = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).build();
+	let progress = ProgressBar::hidden();
+	log::set_max_level(inner.filter());
+	let logger: &'static ProgressLogger = Box::leak(Box::new(ProgressLogger { inner, progress: progress.clone(), num_warnings: AtomicUsize::new(0) }));
+	log::set_logger(logger)?;
+
+	let cli = Cli::parse();
+	match cli.command {
+		Some(Command::Encode { output, inputs, encryption, dither, quantize }) => return encode_file(&output, &inputs, &encryption, dither, quantize),
+		Some(Command::EncodeSprites { output, sprites, metadata, encryption }) => return encode_sprites_file(&output, &sprites, &metadata, &encryption),
+		None => {}
+	}
+
+	let args = cli.args;
+	let input_path = args.input.as_ref().or(args.input_path.as_ref()).ok_or(DecodeError::MissingInput)?;
+	if args.list {
+		return list_images(input_path, args.endian.endian());
+	}
+	if args.json {
+		return print_json(input_path, args.endian.endian());
+	}
+	if args.layout {
+		return print_layout(input_path, args.endian.endian());
+	}
+	if args.check {
+		return check_images(input_path, args.row_aligned, args.endian.endian());
+	}
+	let output_path = if args.stdout {
+		"-"
 	} else {
-		CompressionType::None
+		args.output.as_ref().or(args.output_path.as_ref()).ok_or(DecodeError::MissingOutput)?
 	};
-	let is_encrypted = (flags & 0b10000000) > 0;
-
-	// determine bpp
-	let pixel_data_type = match bytes.get_u8() {
-		0 => PixelDataType::Bpp(1),
-		1 => PixelDataType::Bpp(2),
-		2 => PixelDataType::Bpp(4),
-		3 => PixelDataType::Bpp(8),
-		_ => PixelDataType::Direct
+	let output_dir = if output_path == "-" { None } else { Some(PathBuf::from(output_path)) };
+	let to_stdout = output_dir.is_none();
+
+	let options = DecodeOptions {
+		apply_offsets: args.apply_offsets,
+		decryption_key: args.key,
+		highlight_invalid_indices: args.highlight_invalid,
+		color_format: args.color_format.color_format(),
+		color_expansion: args.color_expansion.color_expansion(),
+		row_aligned: args.row_aligned,
+		tile_order: args.tile_order.tile_order(),
+		transparent_index: args.transparent_index,
+		transparent_color: args.transparent_color,
+		strict: args.strict,
+		sprite_width: args.sprite_width,
+		sprite_height: args.sprite_height,
+		columns: args.columns,
+		gutter: args.gutter,
+		endian: args.endian.endian()
 	};
 
-	// read other properties
-	let num_sprites = bytes.get_u16_le() as usize;
-	let sprite_width_px = bytes.get_u8() as usize;
-	let sprite_height_px = bytes.get_u8() as usize;
-	let offset_x = bytes.get_i8();
-	let offset_y = bytes.get_i8();
-	let image_width = bytes.get_u8() as usize;
-	let image_height = bytes.get_u8() as usize;
-	let _unknown = bytes.get_u8(); // always 17
-	let num_palettes = bytes.get_u8() as usize;
-	let transparent_color_index = bytes.get_u16_le();
-	let palette_data_offset = bytes.get_u16_le() as usize;
-	let pixel_data_offset = bytes.get_u16_le() as usize;
-	let _padding = bytes.get_u16_le(); // always 0
-
-	// calc number of subimages
-	let num_subimages = num_sprites / (image_width * image_height);
-
-	// return image def
-	ImageDef {
-		data_length,
-		has_transparency,
-		is_encrypted,
-		compression,
-		pixel_data_type,
-		num_sprites,
-		num_subimages,
-		sprite_width_px,
-		sprite_height_px,
-		offset_x,
-		offset_y,
-		image_width,
-		image_height,
-		num_palettes,
-		transparent_color_index,
-		palette_data_offset,
-		pixel_data_offset
-	}
-}
-
-fn parse_rgb565(value: u16) -> Rgba<u8> {
-	let r = (value >> 11) * 255 / 31;
-	let g = ((value >> 5) & 0b111111) * 255 / 63;
-	let b = (value & 0b11111) * 255 / 31;
-	Rgba([r as u8, g as u8, b as u8, 255])
-}
-
-fn get_palettes(bytes: &[u8], colors_per_palette: usize, num_palettes: usize) -> Vec<Vec<Rgba<u8>>> {
-	let mut buf = Bytes::copy_from_slice(bytes);
-	let mut palettes = vec![Vec::new(); num_palettes];
-
-	// get all colors
-	let mut colors = Vec::new();
-	while buf.remaining() >= 2 {
-		let value = buf.get_u16_le();
-		let color = parse_rgb565(value);
-		colors.push(color);
+	rayon::ThreadPoolBuilder::new().num_threads(args.jobs).build_global()?;
+
+	// raw palette dumps and JASC palettes would both be written to image-N-palette-M.pal
+	if args.dump_palettes && matches!(args.export_palettes, Some(PaletteFormat::Jasc)) {
+		eprintln!("--dump-palettes can't be combined with --export-palettes jasc");
+		exit(1);
 	}
 
-	// assign colors to palettes
-	for (i, color) in colors.iter().enumerate() {
-		let palette_index = i / colors_per_palette;
-		if palette_index < palettes.len() {
-			palettes[palette_index].push(color.clone());
+	// saving into a missing directory would fail with a bare "not found", so make it up front
+	if let Some(dir) = &output_dir {
+		if dir.is_file() {
+			eprintln!("output path {} is a file, not a directory", dir.display());
+			exit(1);
+		}
+		if let Err(err) = fs::create_dir_all(dir) {
+			eprintln!("couldn't create output directory {}: {}", dir.display(), err);
+			exit(1);
 		}
 	}
 
-	palettes
-}
+	// only show progress to someone watching, never in piped output
+	if !args.quiet && !to_stdout && io::stdout().is_terminal() {
+		progress.set_style(ProgressStyle::with_template("{bar:40} {pos}/{len} {msg}")?);
+		progress.set_draw_target(ProgressDrawTarget::stderr());
+	}
 
-fn get_pixel_data_per_sprite(data: &[u8], def: &ImageDef) -> Vec<Vec<u8>> {
-	if let CompressionType::None = def.compression {
-		get_uncompressed_pixel_data(data, def)
-	} else {
-		get_compressed_pixel_data(data, def)
+	// extract every file in a directory into a folder of its own, skipping files that can't be read
+	if Path::new(input_path).is_dir() {
+		let Some(output_dir) = &output_dir else {
+			eprintln!("writing to stdout needs a single input file");
+			exit(1);
+		};
+		let mut paths: Vec<PathBuf> = fs::read_dir(input_path)?
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|path| path.is_file())
+			.collect();
+		paths.sort();
+		// folders are named after the file without its extension, unless that would put two files
+		// like a.bin and a.dat in the same folder, where they'd overwrite each other
+		let mut num_per_stem: HashMap<&OsStr, usize> = HashMap::new();
+		for path in &paths {
+			*num_per_stem.entry(path.file_stem().unwrap_or_default()).or_default() += 1;
+		}
+		let names: Vec<&OsStr> = paths.iter().map(|path| {
+			let stem = path.file_stem().unwrap_or_default();
+			if num_per_stem[stem] > 1 { path.file_name().unwrap_or_default() } else { stem }
+		}).collect();
+		let mut summaries = Vec::new();
+		for (path, name) in paths.iter().zip(names) {
+			let file_output_dir = output_dir.join(name);
+			info!("{}", path.display());
+			match extract_file(&path.to_string_lossy(), Some(&file_output_dir), &args, &options, &progress, &logger.num_warnings) {
+				Ok(file_summaries) => summaries.push((path.display().to_string(), file_summaries)),
+				Err(err) => warn!("skipping {}: {}", path.display(), err)
+			}
+		}
+		progress.finish_and_clear();
+		if !args.quiet {
+			print_summary(&summaries, logger.num_warnings.load(Ordering::Relaxed));
+		}
+		return Ok(());
+	}
+
+	let summaries = extract_file(input_path, output_dir.as_deref(), &args, &options, &progress, &logger.num_warnings)?;
+	progress.finish_and_clear();
+	if !args.quiet && !to_stdout {
+		print_summary(&[(input_path.clone(), summaries)], logger.num_warnings.load(Ordering::Relaxed));
 	}
+	Ok(())
 }
 
-fn get_uncompressed_pixel_data(data: &[u8], def: &ImageDef) -> Vec<Vec<u8>> {
-	// if uncompressed, each sprite has a fixed size
-	let bytes_per_sprite = if let PixelDataType::Bpp(bpp) = def.pixel_data_type {
-		let bits_per_sprite = def.sprite_width_px * def.sprite_height_px * bpp;
-		if bits_per_sprite % 8 == 0 {
-			bits_per_sprite / 8
-		} else {
-			bits_per_sprite / 8 + 1
+// writes into output_dir, or to stdout without one
+fn extract_file(input_path: &str, output_dir: Option<&Path>, args: &Args, options: &DecodeOptions, progress: &ProgressBar, num_warnings: &AtomicUsize) -> Result<Vec<ImageSummary>, Box<dyn Error + 'static>> {
+	let to_stdout = output_dir.is_none();
+	let data = read_input(input_path)?;
+	let image_offsets = read_offset_table(&data, options.endian)?;
+
+	// make sure every requested image exists before extracting anything
+	let only = args.image.map(|index| vec![index]).or(args.only.clone());
+	if let Some(only) = &only {
+		for index in only {
+			if *index >= image_offsets.len() {
+				return Err(DecodeError::ImageIndexOutOfRange { index: *index, num_images: image_offsets.len() }.into());
+			}
 		}
+	}
+
+	// stdout can only hold a single PNG
+	let num_selected = only.as_ref().map_or(image_offsets.len(), |only| only.len());
+	if to_stdout && num_selected != 1 {
+		eprintln!("writing to stdout needs exactly one image, use --image or --only to pick one");
+		exit(1);
+	}
+	progress.inc_length(num_selected as u64);
+	let stem = if input_path == "-" {
+		"stdin".to_string()
 	} else {
-		def.sprite_width_px * def.sprite_height_px * 2
+		Path::new(input_path).file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned())
 	};
+	let output = Output {
+		dir: output_dir,
+		template: &args.name_template,
+		stem: &stem,
+		index_width: image_offsets.len().saturating_sub(1).to_string().len(),
+		skip_existing: args.skip_existing && !to_stdout
+	};
+
+	// only once the file looks valid, so skipped files in a batch don't leave empty folders behind
+	if let Some(dir) = output_dir {
+		fs::create_dir_all(dir).map_err(|err| format!("couldn't create output directory {}: {}", dir.display(), err))?;
+	}
 
-	let mut pixel_data_per_sprite = Vec::new();
-	for j in 0..def.num_sprites {
-		let a = bytes_per_sprite * j;
-		let b = a + bytes_per_sprite;
-		let pixel_data = if def.is_encrypted {
-			decrypt_pixel_data(&data[a..b])
+	// extract images in parallel, buffering each image's output so it still prints in order. warnings
+	// are only counted by the logger once they're printed, so keep a count of the held ones for progress
+	let num_held_warnings = AtomicUsize::new(0);
+	let logs: Vec<Log> = image_offsets.par_iter().enumerate()
+		.filter(|(i, _)| only.as_ref().is_none_or(|only| only.contains(i)))
+		.map(|(i, image_offset)| {
+			let mut log = Log::default();
+			log.summary.index = i;
+			// the library only logs from the thread decoding an image, but while it waits on its sprites
+			// that thread can pick up another image, so set this image's lines aside until it's done
+			let outer_lines = IMAGE_LINES.replace(Some(Vec::new()));
+			let result = extract_image(&data, i, *image_offset, args, options, &output, &mut log);
+			log.take_held_lines();
+			IMAGE_LINES.set(outer_lines);
+			if let Err(err) = result {
+				log.error(format!("Image {} failed: {}", i, err));
+				log.summary.failed = true;
+			}
+			let num_held = num_held_warnings.fetch_add(log.summary.num_warnings, Ordering::Relaxed) + log.summary.num_warnings;
+			progress.set_message(format!("image {}, {} warnings", i, num_warnings.load(Ordering::Relaxed) + num_held));
+			progress.inc(1);
+			log
+		}).collect();
+	let summaries: Vec<ImageSummary> = logs.into_iter().map(|log| {
+		log.flush();
+		log.summary
+	}).collect();
+
+	// summarize everything in one image
+	if args.contact_sheet && let Some(dir) = output_dir {
+		let spritesheets: Vec<(usize, RgbaImage)> = image_offsets.par_iter().enumerate()
+			.filter(|(i, _)| only.as_ref().is_none_or(|only| only.contains(i)))
+			.filter_map(|(i, image_offset)| {
+				let image = decode_image(&data, *image_offset as usize, options).ok()?;
+				let first_palette = &image.palettes[..image.palettes.len().min(1)];
+				let spritesheet = make_spritesheet(&image.def, &image.pixel_data_per_sprite, first_palette, options).ok()?;
+				Some((i, spritesheet))
+			}).collect();
+		let path = dir.join("index.png");
+		if output.skip_existing && path.exists() {
+			info!("Skipping {}, it already exists", path.display());
 		} else {
-			data[a..b].to_vec()
-		};
-		pixel_data_per_sprite.push(pixel_data);
+			make_contact_sheet(&spritesheets, args.contact_sheet_columns).save(path)?;
+		}
 	}
-	pixel_data_per_sprite
-}
 
-fn get_compressed_pixel_data(data: &[u8], def: &ImageDef) -> Vec<Vec<u8>> {
-	// if compressed, get offsets + lengths and use those to get pixel data per sprite
-	let mut pixel_data_per_sprite = Vec::new();
-	let mut buf = Bytes::copy_from_slice(data);
-	for _ in 0..def.num_sprites {
-		let a = buf.get_u32_le() as usize;
-		let len = buf.get_u32_le() as usize;
-		let pixel_data = if def.is_encrypted {
-			decrypt_pixel_data(&data[a..(a+len)])
+	// a page to browse the spritesheets with, linking them by the names they were just saved as
+	if args.html && let Some(dir) = output_dir {
+		let extension = if args.apng { "png" } else if args.gif { "gif" } else { args.format.image_format().extensions_str()[0] };
+		let images: Vec<(usize, String, ImageDef)> = image_offsets.iter().enumerate()
+			.filter(|(i, _)| only.as_ref().is_none_or(|only| only.contains(i)))
+			.filter_map(|(i, image_offset)| {
+				let def = read_image_def(&data, *image_offset as usize, options.endian).ok()?;
+				Some((i, format!("{}.{}", output.name(i, None, None, None), extension), def))
+			}).collect();
+		let path = dir.join("index.html");
+		if output.skip_existing && path.exists() {
+			info!("Skipping {}, it already exists", path.display());
 		} else {
-			data[a..(a+len)].to_vec()
-		};
-		pixel_data_per_sprite.push(pixel_data);
+			fs::write(path, make_preview_page(&stem, &images))?;
+		}
 	}
-	pixel_data_per_sprite
-}
 
-fn decrypt_pixel_data(data: &[u8]) -> Vec<u8> {
-	data.iter().map(|byte| byte ^ 0x53).collect()
+	Ok(summaries)
 }
 
-fn decompress_bytewise(bytes: &[u8]) -> Vec<u8> {
-	let mut chunks = Vec::new();
-	let mut buf = Bytes::copy_from_slice(bytes);
-	while buf.remaining() >= 1 {
-		let control = buf.get_u8();
-		let top_bit = control >> 7;
-		let n = control & 0x7f;
-		if top_bit == 1 && buf.remaining() >= n as usize {
-			for _ in 0..n {
-				let value = buf.get_u8();
-				chunks.push(value);
+fn extract_image(data: &[u8], i: usize, image_offset: u32, args: &Args, options: &DecodeOptions, output: &Output, log: &mut Log) -> Result<(), Box<dyn Error + Send + Sync>> {
+	// check before decoding, so re-running over a big file only pays for what's missing
+	if output.skip_existing && let Some(paths) = output.known_paths(i, args) && paths.iter().all(|path| path.exists()) {
+		log.info(format!("Skipping image {}, its files already exist", i));
+		return Ok(());
+	}
+
+	log.info(format!("Image {}", i));
+	let image = decode_image(data, image_offset as usize, options)?;
+
+	let image_def = &image.def;
+	log.summary.def = Some(image_def.clone());
+	for line in image_def_lines(image_def) {
+		log.debug(line);
+	}
+
+	// save raw palettes
+	if args.dump_palettes && !output.to_stdout() {
+		// the .pal files are bare colors, so say which one is transparent alongside them
+		let path = output.path(&output.name(i, None, None, None), "-palette.txt");
+		if !output.skip(&path, log) {
+			let text = match (image.palettes.is_empty(), options.transparent_index(image_def)) {
+				(true, _) => "no palettes (direct color)\n".to_string(),
+				(false, Some(index)) => format!("transparent index: {}\n", index),
+				(false, None) => "transparent index: none\n".to_string()
+			};
+			fs::write(&path, text)?;
+			log.wrote(&path);
+		}
+		if image.palettes.is_empty() {
+			log.info(format!("Image {} has no palettes (direct color)", i));
+		} else {
+			for (p, palette) in image.palettes.iter().enumerate() {
+				let path = output.path(&output.name(i, Some(p), None, None), ".pal");
+				if output.skip(&path, log) {
+					continue;
+				}
+				let bytes: Vec<u8> = palette.iter().flat_map(|color| options.color_format.to_raw(*color).to_le_bytes()).collect();
+				fs::write(&path, bytes)?;
+				log.wrote(&path);
+			}
+		}
+	}
+
+	// save palettes for image editors
+	if let Some(palette_format) = args.export_palettes && !output.to_stdout() {
+		for (p, palette) in image.palettes.iter().enumerate() {
+			let name = output.name(i, Some(p), None, None);
+			let path = output.path(&name, &format!(".{}", palette_format.extension()));
+			if output.skip(&path, log) {
+				continue;
 			}
-		} else if top_bit == 0 && buf.remaining() >= 1 {
-			let value = buf.get_u8();
-			for _ in 0..n {
-				chunks.push(value);
+			match palette_format {
+				PaletteFormat::Gpl => fs::write(&path, to_gpl(palette, &name))?,
+				PaletteFormat::Jasc => fs::write(&path, to_jasc(palette))?,
+				PaletteFormat::Act => fs::write(&path, to_act(palette, options.transparent_index(image_def)))?
 			}
+			log.wrote(&path);
 		}
 	}
-	chunks
-}
 
-fn decompress_wordwise(bytes: &[u8]) -> Vec<u8> {
-	let mut chunks = Vec::new();
-	let mut buf = Bytes::copy_from_slice(bytes);
-	while buf.remaining() >= 1 {
-		let control = buf.get_u32_le();
-		let top_bit = control >> 31;
-		let n = (control & 0x0fffffff) as usize;
-		if top_bit > 0 {
-			// add next n chunks
-			for _ in 0..n {
-				let value = buf.get_u32().to_le_bytes();
-				chunks.extend(value.iter());
+	// optionally keep a single palette; direct color images have none to pick from
+	let (first_palette, palettes) = match args.palette {
+		Some(p) if !matches!(image_def.pixel_data_type, PixelDataType::Direct) => {
+			if p >= image.palettes.len() {
+				return Err(format!("palette {} is out of range (image has {} palettes)", p, image.palettes.len()).into());
+			}
+			(p, &image.palettes[p..(p + 1)])
+		},
+		_ => (0, &image.palettes[..])
+	};
+
+	let format = args.format.image_format();
+	let extension = format.extensions_str()[0];
+
+	// save sprites individually, with a manifest of where each one goes
+	if args.split_sprites && !output.to_stdout() {
+		let mut manifest: Vec<ManifestSprite> = sprite_placements(image_def).into_iter()
+			.map(|mut placement| {
+				placement.x *= args.scale;
+				placement.y *= args.scale;
+				ManifestSprite { placement, trim: None, files: Vec::new() }
+			})
+			.collect();
+		for (p, palette) in palette_rows(image_def, palettes).iter().enumerate() {
+			let sprites = make_sprites(image_def, &image.pixel_data_per_sprite, palette, options)?;
+			for (s, sprite) in sprites.into_iter().enumerate() {
+				// transparency comes from the palette index, so every palette row crops like the first
+				if args.trim && p == 0 {
+					manifest[s].trim = Some(match (trim_bounds(&sprite), args.trim_empty) {
+						(Some(bounds), _) => bounds,
+						(None, TrimEmpty::Skip) => Rect { x: 0, y: 0, width: 0, height: 0 },
+						(None, TrimEmpty::Keep) => Rect { x: 0, y: 0, width: sprite.width(), height: sprite.height() }
+					});
+				}
+				let sprite = match manifest[s].trim {
+					Some(bounds) if bounds.width == 0 => continue,
+					Some(bounds) => imageops::crop_imm(&sprite, bounds.x, bounds.y, bounds.width, bounds.height).to_image(),
+					None => sprite
+				};
+				let file = format!("{}.{}", output.name(i, Some(first_palette + p), None, Some(s)), extension);
+				let path = output.path(&file, "");
+				if !output.skip(&path, log) {
+					upscale(flatten(sprite, args.background), args.scale).save_with_format(&path, format)?;
+					log.wrote(&path);
+				}
+				manifest[s].files.push(file);
 			}
+		}
+		for trim in manifest.iter_mut().filter_map(|sprite| sprite.trim.as_mut()) {
+			trim.scale(args.scale);
+		}
+		let path = output.path(&output.name(i, None, None, None), "-sprites.json");
+		if !output.skip(&path, log) {
+			fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+			log.wrote(&path);
+		}
+		return Ok(());
+	}
+
+	// save subimages individually, as animation frames
+	if args.split_subimages && !output.to_stdout() {
+		for (p, palette) in palette_rows(image_def, palettes).iter().enumerate() {
+			let subimages = make_subimages(image_def, &image.pixel_data_per_sprite, palette, options)?;
+			for (j, subimage) in subimages.into_iter().enumerate() {
+				let path = output.path(&output.name(i, Some(first_palette + p), Some(j), None), &format!(".{}", extension));
+				if !output.skip(&path, log) {
+					upscale(flatten(subimage, args.background), args.scale).save_with_format(&path, format)?;
+					log.wrote(&path);
+				}
+			}
+		}
+		return Ok(());
+	}
+
+	// save subimages as an animation
+	if (args.gif || args.apng) && !output.to_stdout() {
+		let palettes = palette_rows(image_def, &image.palettes);
+		let frame_palette = match image_def.pixel_data_type {
+			PixelDataType::Direct => args.frame_palette.unwrap_or(0),
+			_ => args.frame_palette.or(args.palette).unwrap_or(0)
+		};
+		let palette = palettes.get(frame_palette)
+			.ok_or(format!("palette {} is out of range (image has {} palettes)", frame_palette, palettes.len()))?;
+		let path = output.path(&output.name(i, None, None, None), if args.apng { ".png" } else { ".gif" });
+		if output.skip(&path, log) {
+			return Ok(());
+		}
+		let subimages: Vec<RgbaImage> = make_subimages(image_def, &image.pixel_data_per_sprite, palette, options)?.into_iter()
+			.map(|subimage| upscale(flatten(subimage, args.background), args.scale))
+			.collect();
+		if args.apng {
+			write_apng(&path, &subimages, args.fps, args.loops)?;
 		} else {
-			// repeat [value] n times
-			let value = buf.get_u32().to_le_bytes();
-			for _ in 0..n {
-				chunks.extend(value.iter());
+			write_gif(&path, subimages, args.fps)?;
+		}
+		log.wrote(&path);
+		return Ok(());
+	}
+
+	// save palette indices with each palette, instead of colors
+	if args.indexed_png && !output.to_stdout() && let Some(sheet) = make_index_sheet(image_def, &image.pixel_data_per_sprite, options)? {
+		let sheet = upscale(sheet, args.scale);
+		for (p, palette) in palettes.iter().enumerate() {
+			let path = output.path(&output.name(i, Some(first_palette + p), None, None), ".png");
+			if output.skip(&path, log) {
+				continue;
 			}
+			write_indexed_png(&path, &sheet, palette, index_sheet_fill(image_def, options).map(usize::from))?;
+			log.wrote(&path);
 		}
+		return Ok(());
 	}
-	chunks
-}
 
-fn byte_to_bits(byte: u8) -> Vec<u8> {
-	let mut bits = Vec::new();
-	for i in 0..8 {
-		bits.push((byte >> i) & 1);
+	// combine sprites into subimages, and subimages into a spritesheet, one row per palette
+	let name = output.name(i, None, None, None);
+	let path = output.path(&name, &format!(".{}", extension));
+	if !output.skip(&path, log) {
+		let spritesheet = make_spritesheet(image_def, &image.pixel_data_per_sprite, palettes, options)?;
+		let spritesheet = upscale(flatten(spritesheet, args.background), args.scale);
+
+		// save spritesheet
+		if spritesheet.width() == 0 || spritesheet.height() == 0 {
+			log.error(format!("Image {} is empty, skipping", i));
+			return Ok(());
+		}
+		if output.to_stdout() {
+			let mut encoded = Cursor::new(Vec::new());
+			spritesheet.write_to(&mut encoded, format)?;
+			io::stdout().write_all(encoded.get_ref())?;
+			return Ok(());
+		}
+		spritesheet.save_with_format(&path, format)?;
+		log.wrote(&path);
+	}
+
+	// save sprite rectangles next to the spritesheet
+	let path = output.path(&name, "-atlas.json");
+	if args.atlas && !output.skip(&path, log) {
+		let mut atlas = make_atlas(image_def, palettes, options);
+		atlas.scale(args.scale);
+		let json = serde_json::to_string_pretty(&atlas)?;
+		fs::write(&path, json)?;
+		log.wrote(&path);
+	}
+
+	// save header values and palettes next to the spritesheet
+	let path = output.path(&name, ".json");
+	if args.metadata && !output.skip(&path, log) {
+		let palettes = image.palettes.iter().map(|palette|
+			palette.iter().map(|color| options.color_format.to_raw(*color)).collect()
+		).collect();
+		let metadata = ImageMetadata { offset: image_offset, def: image.def, color_format: options.color_format, palettes, row_aligned: options.row_aligned, tile_order: options.tile_order, endian: options.endian };
+		let json = serde_json::to_string_pretty(&metadata)?;
+		fs::write(&path, json)?;
+		log.wrote(&path);
 	}
-	bits
+
+	Ok(())
 }
 
-fn bits_to_byte(bits: &[u8]) -> u8 {
-	let mut byte = 0;
-	for (i, bit) in bits.iter().enumerate() {
-		byte = byte | (bit << i);
+// a line per image, then totals and the kinds of images seen, to get a feel for an unknown file.
+// the warning total also counts warnings that don't belong to any one image
+fn print_summary(files: &[(String, Vec<ImageSummary>)], num_warnings: usize) {
+	let mut compressions = Vec::new();
+	let mut colors = Vec::new();
+	let (mut num_images, mut num_failed, mut num_warned, mut num_sprites, mut bytes_written) = (0, 0, 0, 0, 0);
+	for (file, summaries) in files {
+		if files.len() > 1 {
+			println!("{}", file);
+		}
+		println!("{:>6}  {:<12} {:<13} {:>8} {:>10} {:>8}", "image", "compression", "color", "sprites", "bytes", "warnings");
+		for summary in summaries {
+			let (compression, color, sprites) = match &summary.def {
+				Some(def) => {
+					let color = match def.pixel_data_type {
+						PixelDataType::Bpp(bpp) => format!("{} bpp", bpp),
+						PixelDataType::Direct => "direct".to_string()
+					};
+					(format!("{:?}", def.compression), color, def.num_sprites.to_string())
+				},
+				None => ("-".to_string(), "-".to_string(), "-".to_string())
+			};
+			let status = if summary.failed { "  failed" } else if summary.def.is_none() { "  skipped" } else { "" };
+			println!("{:>6}  {:<12} {:<13} {:>8} {:>10} {:>8}{}", summary.index, compression, color, sprites, summary.bytes_written, summary.num_warnings, status);
+
+			num_images += 1;
+			num_failed += summary.failed as usize;
+			num_warned += (summary.num_warnings > 0) as usize;
+			num_sprites += summary.def.as_ref().map_or(0, |def| def.num_sprites);
+			bytes_written += summary.bytes_written;
+			if summary.def.is_some() {
+				if !compressions.contains(&compression) {
+					compressions.push(compression);
+				}
+				if !colors.contains(&color) {
+					colors.push(color);
+				}
+			}
+		}
 	}
-	byte
+	println!("{} images ({} failed, {} with warnings), {} sprites, {} warnings, {} bytes written", num_images, num_failed, num_warned, num_sprites, num_warnings, bytes_written);
+	println!("compression: {}", compressions.join(", "));
+	println!("color: {}", colors.join(", "));
 }
 
-fn make_sprite(data: &[u8], def: &ImageDef, palette: &[Rgba<u8>]) -> RgbaImage {
-	// decompress pixel data
-	let pixel_data = match def.compression {
-		CompressionType::None => data.to_vec(),
-		CompressionType::Bytewise => decompress_bytewise(&data),
-		CompressionType::Wordwise => decompress_wordwise(&data)
+// blends every pixel over the background by its alpha, leaving the image fully opaque
+fn flatten(mut img: RgbaImage, background: Option<[u8; 3]>) -> RgbaImage {
+	let Some(background) = background else {
+		return img;
 	};
+	for pixel in img.pixels_mut() {
+		let alpha = pixel[3] as u32;
+		for channel in 0..3 {
+			pixel[channel] = ((pixel[channel] as u32 * alpha + background[channel] as u32 * (255 - alpha) + 127) / 255) as u8;
+		}
+		pixel[3] = 255;
+	}
+	img
+}
 
-	// convert pixel data to images
-	let sprite = if let PixelDataType::Bpp(bpp) = def.pixel_data_type {
-		make_indexed_sprite(&pixel_data, &def, bpp, &palette)
-	} else {
-		make_direct_sprite(&pixel_data, &def)
-	};
+// nearest-neighbor, so every pixel becomes a sharp scale x scale block
+fn upscale<P: Pixel<Subpixel = u8> + 'static>(img: ImageBuffer<P, Vec<u8>>, scale: u32) -> ImageBuffer<P, Vec<u8>> {
+	if scale == 1 {
+		return img;
+	}
+	imageops::resize(&img, img.width() * scale, img.height() * scale, FilterType::Nearest)
+}
 
-	sprite
+// fully transparent pixels become the GIF transparent color, partial alpha is lost
+fn write_gif(path: &Path, frames: Vec<RgbaImage>, fps: u16) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let delay = Delay::from_numer_denom_ms(1000, fps as u32);
+	let mut encoder = GifEncoder::new(fs::File::create(path)?);
+	encoder.set_repeat(Repeat::Infinite)?;
+	encoder.encode_frames(frames.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))?;
+	Ok(())
 }
 
-fn make_indexed_sprite(bytes: &[u8], def: &ImageDef, bpp: usize, palette: &[Rgba<u8>]) -> RgbaImage {
-	let mut img = RgbaImage::new(def.sprite_width_px as u32, def.sprite_height_px as u32);
-	let mut buf = Bytes::copy_from_slice(bytes);
+// keeps the full alpha channel, unlike GIF
+fn write_apng(path: &Path, frames: &[RgbaImage], fps: u16, loops: u32) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let (width, height) = frames.first().map_or((0, 0), |frame| frame.dimensions());
+	let mut encoder = png::Encoder::new(io::BufWriter::new(fs::File::create(path)?), width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_animated(frames.len() as u32, loops)?;
+	encoder.set_frame_delay(1, fps)?;
+	let mut writer = encoder.write_header()?;
+	for frame in frames {
+		writer.write_image_data(frame.as_raw())?;
+	}
+	writer.finish()?;
+	Ok(())
+}
 
-	// add bits to end of stream in least-significant order
-	let mut bits = Vec::new();
-	while buf.remaining() >= 1 {
-		bits.extend(byte_to_bits(buf.get_u8()));
+// palette entries missing from the file show up magenta, like out-of-range indices do with --highlight-invalid,
+// unless they're the transparent index make_index_sheet fills empty areas with
+fn write_indexed_png(path: &Path, sheet: &GrayImage, palette: &[Rgba<u8>], transparent_index: Option<usize>) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let num_colors = sheet.pixels().map(|pixel| pixel[0] as usize + 1).max().unwrap_or(0).max(palette.len()).min(256);
+	let mut plte = Vec::new();
+	for index in 0..num_colors {
+		let color = palette.get(index).copied().unwrap_or(Rgba([255, 0, 255, 255]));
+		plte.extend(&color.0[..3]);
 	}
 
-	// divide bits into chunks of n bits, where n is bpp (bits per pixel)
-	let chunks = bits.chunks(bpp);
-	let expected_chunks = def.sprite_width_px * def.sprite_height_px;
-	if chunks.len() != expected_chunks {
-		println!("WARNING: expected {} chunks, got {}", expected_chunks, chunks.len());
+	let mut encoder = png::Encoder::new(io::BufWriter::new(fs::File::create(path)?), sheet.width(), sheet.height());
+	encoder.set_color(png::ColorType::Indexed);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_palette(plte);
+	if let Some(index) = transparent_index && index < num_colors {
+		let mut trns = vec![255; index + 1];
+		trns[index] = 0;
+		encoder.set_trns(trns);
 	}
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(sheet.as_raw())?;
+	writer.finish()?;
+	Ok(())
+}
 
-	// convert each chunk into a palette index and draw pixel
-	for (i, chunk) in chunks.enumerate() {
-		let x = i % def.sprite_width_px;
-		let y = i / def.sprite_width_px;
-		let index = bits_to_byte(chunk) as usize;
-		let color = if def.has_transparency && index == def.transparent_color_index as usize {
-			Rgba([0, 0, 0, 0])
-		} else {
-			palette.get(index).expect("color index is out of range for given palette").clone()
+fn image_def_lines(image_def: &ImageDef) -> Vec<String> {
+	vec![
+		format!("is_encrypted: {:?}", image_def.is_encrypted),
+		format!("compression: {:?}", image_def.compression),
+		format!("num_palettes: {}", image_def.num_palettes),
+		format!("num_sprites: {}", image_def.num_sprites),
+		format!("sprite_width_px: {}", image_def.sprite_width_px),
+		format!("sprite_height_px: {}", image_def.sprite_height_px),
+		format!("image_width: {}", image_def.image_width),
+		format!("image_height: {}", image_def.image_height)
+	]
+}
+
+// prints the header of every image, skipping palette and pixel decoding
+fn list_images(input_path: &str, endian: Endian) -> Result<(), Box<dyn Error + 'static>> {
+	let data = read_input(input_path)?;
+	let image_offsets = read_offset_table(&data, endian)?;
+	for (i, image_offset) in image_offsets.iter().enumerate() {
+		println!("\nImage {}", i);
+		println!("    offset: {}", image_offset);
+		match read_image_def(&data, *image_offset as usize, endian) {
+			Ok(image_def) => {
+				println!("    data_length: {}", image_def.data_length);
+				for line in image_def_lines(&image_def) {
+					println!("    {}", line);
+				}
+			},
+			Err(err) => error!("Image {} failed: {}", i, err)
+		}
+	}
+	Ok(())
+}
+
+// prints the absolute file offsets of every region, in file order, so unaccounted bytes stand out
+fn print_layout(input_path: &str, endian: Endian) -> Result<(), Box<dyn Error + 'static>> {
+	let data = read_input(input_path)?;
+	let image_offsets = read_offset_table(&data, endian)?;
+	let mut images: Vec<(usize, usize)> = image_offsets.iter().map(|offset| *offset as usize).enumerate().collect();
+	images.sort_by_key(|(_, offset)| *offset);
+
+	println!("offset table: 0-{}", image_offsets.len() * 4);
+	println!("{:>6} {:>10} {:>10} {:>10} {:>10} {:>8}", "image", "header", "palettes", "pixels", "end", "gap");
+	// gaps are unknown after an image whose header can't be read
+	let mut previous_end = Some(image_offsets.len() * 4);
+	for (i, offset) in images {
+		let gap = previous_end.map_or("-".to_string(), |end| (offset as isize - end as isize).to_string());
+		match read_image_def(&data, offset, endian) {
+			Ok(image_def) => {
+				let end = offset + image_def.data_length;
+				println!("{:>6} {:>10} {:>10} {:>10} {:>10} {:>8}", i, offset, offset + image_def.palette_data_offset, offset + image_def.pixel_data_offset, end, gap);
+				previous_end = Some(end);
+			},
+			Err(err) => {
+				println!("{:>6} {:>10} {:>10} {:>10} {:>10} {:>8}", i, offset, "-", "-", "-", gap);
+				error!("Image {} failed: {}", i, err);
+				previous_end = None;
+			}
+		}
+	}
+	match previous_end {
+		Some(end) if end < data.len() => println!("{} trailing bytes after the last image", data.len() - end),
+		Some(end) if end > data.len() => println!("last image ends {} bytes past the end of the file", end - data.len()),
+		_ => {}
+	}
+	Ok(())
+}
+
+// runs header checks on every image, exiting with an error if any fail. palette mismatches are only
+// warnings, since the image may still decode
+fn check_images(input_path: &str, row_aligned: bool, endian: Endian) -> Result<(), Box<dyn Error + 'static>> {
+	let data = read_input(input_path)?;
+	let image_offsets = read_offset_table(&data, endian)?;
+	let mut num_failed = 0;
+	for (i, image_offset) in image_offsets.iter().enumerate() {
+		let (problems, warnings) = match read_image_def(&data, *image_offset as usize, endian) {
+			Ok(image_def) => (check_image_def(&image_def, *image_offset as usize, data.len(), row_aligned), image_def.palette_mismatches()),
+			Err(err) => (vec![err.to_string()], Vec::new())
 		};
-		if x < def.sprite_width_px && y < def.sprite_height_px {
-			img.put_pixel(x as u32, y as u32, color);
+		if problems.is_empty() {
+			println!("Image {}: PASS", i);
+		} else {
+			num_failed += 1;
+			println!("Image {}: FAIL", i);
+			for problem in problems {
+				println!("    {}", problem);
+			}
+		}
+		for warning in warnings {
+			println!("    warning: {}", warning);
 		}
 	}
+	if num_failed > 0 {
+		eprintln!("\n{} of {} images failed", num_failed, image_offsets.len());
+		exit(1);
+	}
+	Ok(())
+}
 
-	img
+fn check_image_def(image_def: &ImageDef, image_offset: usize, file_len: usize, row_aligned: bool) -> Vec<String> {
+	let mut problems = Vec::new();
+	if image_def.palette_data_offset > image_def.pixel_data_offset {
+		problems.push(format!("palette_data_offset {} is past pixel_data_offset {}", image_def.palette_data_offset, image_def.pixel_data_offset));
+	}
+	if image_def.pixel_data_offset >= image_def.data_length {
+		problems.push(format!("pixel_data_offset {} is not before data_length {}", image_def.pixel_data_offset, image_def.data_length));
+	}
+	if image_offset + image_def.data_length > file_len {
+		problems.push(format!("image ends at {}, past the end of the file ({} bytes)", image_offset + image_def.data_length, file_len));
+	}
+	if let CompressionType::None = image_def.compression {
+		let needed = image_def.bytes_per_sprite(row_aligned) * image_def.num_sprites;
+		let available = image_def.data_length.saturating_sub(image_def.pixel_data_offset);
+		if needed > available {
+			problems.push(format!("{} sprites need {} bytes of pixel data, but only {} are available", image_def.num_sprites, needed, available));
+		}
+	}
+	if image_def.num_subimages * image_def.image_width * image_def.image_height < image_def.num_sprites {
+		problems.push(format!("{} subimages of {}x{} can't hold {} sprites", image_def.num_subimages, image_def.image_width, image_def.image_height, image_def.num_sprites));
+	}
+	problems
 }
 
-fn make_direct_sprite(bytes: &[u8], def: &ImageDef) -> RgbaImage {
-	let mut img = RgbaImage::new(def.sprite_width_px as u32, def.sprite_height_px as u32);
-	let mut buf = Bytes::copy_from_slice(bytes);
-	let mut i = 0;
-	while bytes.remaining() >= 2 {
-		let x = i % def.sprite_width_px;
-		let y = i / def.sprite_width_px;
-		let value = buf.get_u16_le();
-		let mut color = parse_rgb565(value);
-		if def.has_transparency && def.transparent_color_index == value {
-			color = Rgba([0, 0, 0, 0]);
+// prints the header of every image as JSON, skipping images whose header can't be read
+fn print_json(input_path: &str, endian: Endian) -> Result<(), Box<dyn Error + 'static>> {
+	let data = read_input(input_path)?;
+	let image_offsets = read_offset_table(&data, endian)?;
+	let mut records = Vec::new();
+	for (index, offset) in image_offsets.iter().enumerate() {
+		match read_image_def(&data, *offset as usize, endian) {
+			Ok(def) => records.push(ImageRecord { index, offset: *offset, def }),
+			Err(err) => error!("Image {} failed: {}", index, err)
 		}
-		img.put_pixel(x as u32, y as u32, color);
-		i += 1;
 	}
-	img
+	println!("{}", serde_json::to_string_pretty(&records)?);
+	Ok(())
 }
 
-fn make_subimage(sprites: &[RgbaImage], def: &ImageDef) -> RgbaImage {
-	let width = def.sprite_width_px * def.image_width;
-	let height = def.sprite_height_px * def.image_height;
-	let mut img = RgbaImage::new(width as u32, height as u32);
-	for (i, sprite) in sprites.iter().enumerate() {
-		let x = (i % def.image_width) * def.sprite_width_px;
-		let y = (i / def.image_width) * def.sprite_height_px;
-		img.copy_from(sprite, x as u32, y as u32).expect("unable to copy sprite into subimage");
+// reads the whole input file, or stdin if the path is -
+fn read_input(input_path: &str) -> Result<Vec<u8>, DecodeError> {
+	if input_path == "-" {
+		let mut data = Vec::new();
+		io::stdin().read_to_end(&mut data)?;
+		Ok(data)
+	} else {
+		Ok(fs::read(input_path)?)
 	}
-	img
 }
 
-fn make_spritesheet(def: &ImageDef, pixel_data_per_sprite: &[Vec<u8>], palettes: &[Vec<Rgba<u8>>]) -> RgbaImage {
-	let sprites_per_subimage = def.image_width * def.image_height;
-	let spritesheet_width = def.num_subimages * def.image_width * def.sprite_width_px;
-	let spritesheet_height = def.num_palettes * def.image_height * def.sprite_height_px;
-	let mut img = RgbaImage::new(spritesheet_width as u32, spritesheet_height as u32);
-	for (i, palette) in palettes.iter().enumerate() {
-		let sprites: Vec<RgbaImage> = pixel_data_per_sprite.iter().map(|pixel_data|
-			make_sprite(pixel_data, def, palette)
-		).collect();
-		let subimages: Vec<RgbaImage> = (0..def.num_subimages).map(|j| {
-			let a = j * sprites_per_subimage;
-			let b = a + sprites_per_subimage;
-			make_subimage(&sprites[a..b], def)
-		}).collect();
-		for (j, subimage) in subimages.iter().enumerate() {
-			let x = j * def.image_width * def.sprite_width_px;
-			let y = i * def.image_height * def.sprite_height_px;
-			img.copy_from(subimage, x as u32, y as u32).expect("unable to copy subimage into spritesheet");
+// rebuilds a file from pairs of spritesheets and metadata sidecars
+fn encode_file(output_path: &str, inputs: &[String], encryption: &Encryption, dither: bool, quantize: bool) -> Result<(), Box<dyn Error + 'static>> {
+	if !inputs.len().is_multiple_of(2) {
+		return Err("expected pairs of spritesheet and metadata paths".into());
+	}
+
+	let mut images = Vec::new();
+	for pair in inputs.chunks(2) {
+		let spritesheet = image::open(&pair[0])?.into_rgba8();
+		let mut metadata: ImageMetadata = serde_json::from_slice(&fs::read(&pair[1])?)?;
+		metadata.def.is_encrypted |= encryption.encrypt;
+		images.push((metadata, spritesheet));
+	}
+
+	let data = encode(&images, &EncodeOptions { encryption_key: encryption.key, dither, quantize })?;
+	fs::write(output_path, data)?;
+
+	Ok(())
+}
+
+// rebuilds a file from the sprites and table of contents written by --split-sprites and --json
+fn encode_sprites_file(output_path: &str, sprites_path: &str, metadata_path: &str, encryption: &Encryption) -> Result<(), Box<dyn Error + 'static>> {
+	let records: Vec<ImageRecord> = serde_json::from_slice(&fs::read(metadata_path)?)?;
+
+	// find sprites by image and sprite number, however the image index was padded
+	let mut sprite_paths = HashMap::new();
+	for entry in fs::read_dir(sprites_path)? {
+		let path = entry?.path();
+		let Some(name) = path.file_stem().and_then(|name| name.to_str()) else { continue };
+		if let Some((index, sprite)) = name.strip_prefix("image-").and_then(|name| name.split_once("-palette-0-sprite-"))
+			&& let (Ok(index), Ok(sprite)) = (index.parse::<usize>(), sprite.parse::<usize>()) {
+			sprite_paths.insert((index, sprite), path);
 		}
 	}
-	img
+
+	let mut images = Vec::new();
+	for record in &records {
+		let sprites = (0..record.def.num_sprites).map(|s| {
+			let path = sprite_paths.get(&(record.index, s))
+				.ok_or(format!("no sprite file for image {} sprite {}", record.index, s))?;
+			Ok(image::open(path)?.into_rgba8())
+		}).collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+		let (mut metadata, spritesheet) = sprites_to_image(&record.def, &sprites)?;
+		metadata.def.is_encrypted = encryption.encrypt;
+		images.push((metadata, spritesheet));
+	}
+
+	let data = encode(&images, &EncodeOptions { encryption_key: encryption.key, ..Default::default() })?;
+	fs::write(output_path, data)?;
+
+	Ok(())
+}
+
+// parses hex values like "0x53" as well as plain decimal
+fn parse_byte(value: &str) -> Result<u8, Box<dyn Error + Send + Sync + 'static>> {
+	if let Some(hex) = value.strip_prefix("0x") {
+		Ok(u8::from_str_radix(hex, 16)?)
+	} else {
+		Ok(value.parse()?)
+	}
+}
+
+// parses hex colors like "ff00ff", with or without a leading #
+fn parse_rgb(value: &str) -> Result<[u8; 3], Box<dyn Error + Send + Sync + 'static>> {
+	let hex = value.strip_prefix('#').unwrap_or(value);
+	if hex.len() != 6 {
+		return Err("expected a color like RRGGBB".into());
+	}
+	let rgb = u32::from_str_radix(hex, 16)?;
+	Ok([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
+
+// checks every placeholder in an output name template, dropping any extension since every output
+// adds its own
+fn parse_name_template(value: &str) -> Result<String, Box<dyn Error + Send + Sync + 'static>> {
+	let mut rest = value;
+	while let Some(start) = rest.find('{') {
+		let end = start + rest[start..].find('}').ok_or("unclosed { in name template")?;
+		let placeholder = &rest[(start + 1)..end];
+		if parse_placeholder(placeholder).is_none() {
+			return Err(format!("unknown placeholder {{{}}}, expected index, stem, palette, frame, subimage or sprite", placeholder).into());
+		}
+		rest = &rest[(end + 1)..];
+	}
+	let template = [".png", ".bmp", ".tga", ".webp", ".gif"].iter()
+		.find_map(|extension| value.strip_suffix(extension))
+		.unwrap_or(value);
+	Ok(template.to_string())
+}
+
+// splits a placeholder like "index:03" into its name and zero-padded width. {subimage} is another
+// name for {frame}, and only numbers take a width
+fn parse_placeholder(placeholder: &str) -> Option<(&'static str, Option<usize>)> {
+	let (key, width) = match placeholder.split_once(':') {
+		Some((key, width)) => (key, Some(width.parse().ok()?)),
+		None => (placeholder, None)
+	};
+	let key = match key {
+		"index" => "index",
+		"stem" if width.is_none() => "stem",
+		"palette" => "palette",
+		"frame" | "subimage" => "frame",
+		"sprite" => "sprite",
+		_ => return None
+	};
+	Some((key, width))
+}
+
+// parses lists like "3,5,12" and ranges like "10-15"
+fn parse_index_list(value: &str) -> Result<Indices, Box<dyn Error + Send + Sync + 'static>> {
+	let mut indices = Vec::new();
+	for part in value.split(',') {
+		if let Some((start, end)) = part.split_once('-') {
+			let start: usize = start.trim().parse()?;
+			let end: usize = end.trim().parse()?;
+			if start > end {
+				return Err(format!("range {} ends before it starts", part.trim()).into());
+			}
+			indices.extend(start..=end);
+		} else {
+			indices.push(part.trim().parse()?);
+		}
+	}
+	Ok(indices)
 }
@@ -0,0 +1,35 @@
+use bytes::BufMut;
+use image::Rgba;
+
+// GIMP palette, one "R G B Name" line per color
+pub fn to_gpl(palette: &[Rgba<u8>], name: &str) -> String {
+	let mut gpl = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", name);
+	for (i, color) in palette.iter().enumerate() {
+		gpl.push_str(&format!("{:3} {:3} {:3}\tIndex {}\n", color[0], color[1], color[2], i));
+	}
+	gpl
+}
+
+// Paint Shop Pro palette, one "R G B" line per color
+pub fn to_jasc(palette: &[Rgba<u8>]) -> String {
+	let mut jasc = format!("JASC-PAL\r\n0100\r\n{}\r\n", palette.len());
+	for color in palette {
+		jasc.push_str(&format!("{} {} {}\r\n", color[0], color[1], color[2]));
+	}
+	jasc
+}
+
+// Adobe color table: 256 RGB entries, zero-padded, plus a footer with the color count and
+// transparent index when there is one
+pub fn to_act(palette: &[Rgba<u8>], transparent_index: Option<usize>) -> Vec<u8> {
+	let mut act = Vec::new();
+	for i in 0..256 {
+		let color = palette.get(i).copied().unwrap_or(Rgba([0, 0, 0, 0]));
+		act.extend(&color.0[..3]);
+	}
+	if let Some(index) = transparent_index {
+		act.put_u16(palette.len().min(256) as u16);
+		act.put_u16(index as u16);
+	}
+	act
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use image::{ Rgba, RgbaImage };
+
+// a set of colors that will share one palette entry, along with how many pixels use each
+struct ColorBox {
+	colors: Vec<([u8; 3], usize)>
+}
+
+impl ColorBox {
+	// the channel with the widest spread of values, and that spread
+	fn widest_channel(&self) -> (usize, u8) {
+		(0..3).map(|channel| {
+			let min = self.colors.iter().map(|(color, _)| color[channel]).min().unwrap_or(0);
+			let max = self.colors.iter().map(|(color, _)| color[channel]).max().unwrap_or(0);
+			(channel, max - min)
+		}).max_by_key(|(_, range)| *range).unwrap_or((0, 0))
+	}
+
+	// splits at the median pixel along the widest channel, so both halves cover about as many pixels
+	fn split(mut self) -> (ColorBox, ColorBox) {
+		let (channel, _) = self.widest_channel();
+		self.colors.sort_by_key(|(color, _)| color[channel]);
+		let total: usize = self.colors.iter().map(|(_, count)| count).sum();
+		let mut seen = 0;
+		let mut at = 1;
+		for (i, (_, count)) in self.colors.iter().enumerate() {
+			seen += count;
+			if seen * 2 >= total {
+				at = i + 1;
+				break;
+			}
+		}
+		let at = at.clamp(1, self.colors.len() - 1);
+		let rest = self.colors.split_off(at);
+		(self, ColorBox { colors: rest })
+	}
+
+	// average of every pixel in the box
+	fn average(&self) -> Rgba<u8> {
+		let total: usize = self.colors.iter().map(|(_, count)| count).sum::<usize>().max(1);
+		let mut sums = [0; 3];
+		for (color, count) in &self.colors {
+			for channel in 0..3 {
+				sums[channel] += color[channel] as usize * count;
+			}
+		}
+		Rgba([(sums[0] / total) as u8, (sums[1] / total) as u8, (sums[2] / total) as u8, 255])
+	}
+}
+
+// reduces an image to at most 2^bpp colors with median cut, returning the palette and the palette index
+// of every pixel in row order. fully transparent pixels get index 0, which is kept free for them
+pub fn quantize(image: &RgbaImage, bpp: usize) -> (Vec<Rgba<u8>>, Vec<u8>) {
+	let max_colors = 1usize << bpp.min(8);
+	let has_transparency = image.pixels().any(|color| color[3] == 0);
+	let mut palette = if has_transparency { vec![Rgba([0, 0, 0, 0])] } else { Vec::new() };
+
+	let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+	for color in image.pixels().filter(|color| color[3] != 0) {
+		*counts.entry([color[0], color[1], color[2]]).or_default() += 1;
+	}
+
+	// keep splitting the box with the widest channel until the palette is full or every color has its own box
+	let max_boxes = max_colors - palette.len();
+	let mut boxes = Vec::new();
+	if !counts.is_empty() && max_boxes > 0 {
+		boxes.push(ColorBox { colors: counts.into_iter().collect() });
+	}
+	while boxes.len() < max_boxes {
+		let widest = boxes.iter().enumerate()
+			.filter(|(_, color_box)| color_box.colors.len() > 1)
+			.max_by_key(|(_, color_box)| color_box.widest_channel().1)
+			.map(|(i, _)| i);
+		let Some(i) = widest else { break };
+		let (left, right) = boxes.swap_remove(i).split();
+		boxes.push(left);
+		boxes.push(right);
+	}
+
+	// every color maps to the entry for the box it ended up in
+	let mut indices_by_color = HashMap::new();
+	for color_box in &boxes {
+		let index = palette.len() as u8;
+		for (color, _) in &color_box.colors {
+			indices_by_color.insert(*color, index);
+		}
+		palette.push(color_box.average());
+	}
+
+	let indices = image.pixels().map(|color| {
+		if color[3] == 0 {
+			0
+		} else {
+			// only missing when a 1 color palette is taken up by transparency
+			indices_by_color.get(&[color[0], color[1], color[2]]).copied().unwrap_or(0)
+		}
+	}).collect();
+
+	(palette, indices)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn palette_fits_bpp_and_keeps_index_0_for_transparency() {
+		let image = RgbaImage::from_fn(16, 16, |x, y| {
+			if x == y { Rgba([0, 0, 0, 0]) } else { Rgba([(x * 16) as u8, (y * 16) as u8, 128, 255]) }
+		});
+		for bpp in [1, 2, 4, 8] {
+			let (palette, indices) = quantize(&image, bpp);
+			assert!(palette.len() <= 1 << bpp);
+			assert_eq!(palette[0][3], 0);
+			for (pixel, index) in image.pixels().zip(&indices) {
+				assert!((*index as usize) < palette.len());
+				assert_eq!(pixel[3] == 0, *index == 0);
+			}
+		}
+	}
+
+	#[test]
+	fn opaque_images_use_every_entry_for_colors() {
+		let image = RgbaImage::from_fn(4, 1, |x, _| Rgba([(x * 60) as u8, 0, 0, 255]));
+		let (palette, indices) = quantize(&image, 2);
+		assert_eq!(palette.len(), 4);
+		for (pixel, index) in image.pixels().zip(&indices) {
+			assert_eq!(palette[*index as usize], *pixel);
+		}
+	}
+}
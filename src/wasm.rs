@@ -0,0 +1,52 @@
+use std::io::Cursor;
+use image::ImageFormat;
+use js_sys::{ Array, Uint8Array };
+use wasm_bindgen::prelude::*;
+use crate::{ DecodeOptions, build_spritesheet, make_spritesheet, read_offset_table };
+
+// a decoded spritesheet as raw RGBA8 pixels, row by row
+#[wasm_bindgen]
+pub struct Spritesheet {
+	pub width: u32,
+	pub height: u32,
+	pixels: Vec<u8>
+}
+
+#[wasm_bindgen]
+impl Spritesheet {
+	#[wasm_bindgen(getter)]
+	pub fn pixels(&self) -> Vec<u8> {
+		self.pixels.clone()
+	}
+}
+
+// decodes the image at index into a spritesheet, with one row per palette
+#[wasm_bindgen(js_name = decodeImage)]
+pub fn decode_image(data: &[u8], index: usize) -> Result<Spritesheet, JsValue> {
+	let spritesheet = build_spritesheet(data, index).map_err(to_js_error)?;
+	Ok(Spritesheet {
+		width: spritesheet.width(),
+		height: spritesheet.height(),
+		pixels: spritesheet.into_raw()
+	})
+}
+
+// decodes every image into a spritesheet and encodes each one as PNG, returning an array of
+// Uint8Arrays in file order
+#[wasm_bindgen(js_name = decodeToPngBlobs)]
+pub fn decode_to_png_blobs(data: &[u8]) -> Result<JsValue, JsValue> {
+	let options = DecodeOptions::default();
+	let blobs = Array::new();
+	for image_offset in read_offset_table(data, options.endian).map_err(to_js_error)? {
+		let image = crate::decode_image(data, image_offset as usize, &options).map_err(to_js_error)?;
+		let spritesheet = make_spritesheet(&image.def, &image.pixel_data_per_sprite, &image.palettes, &options).map_err(to_js_error)?;
+		let mut png = Cursor::new(Vec::new());
+		spritesheet.write_to(&mut png, ImageFormat::Png).map_err(to_js_error)?;
+		blobs.push(&Uint8Array::from(png.get_ref().as_slice()));
+	}
+	Ok(blobs.into())
+}
+
+fn to_js_error(err: impl ToString) -> JsValue {
+	JsValue::from_str(&err.to_string())
+}